@@ -0,0 +1,111 @@
+//! Generations per second of the bounded grids on standard workloads, for both
+//! border policies. Run with `cargo bench`, criterion keeping the last run as
+//! the baseline the next one is compared against.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use conways_tui::{
+    border_policy::BorderPolicy, double_buffer_grid::DoubleBufferGrid, packed_grid::PackedGrid,
+    pattern::rle, rule::Rule,
+};
+
+/// Generations computed by each iteration.
+const GENERATIONS: u64 = 20;
+
+const GOSPER_GLIDER_GUN: &str = "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+
+const ACORN: &str = "x = 7, y = 3, rule = B3/S23
+bo$3bo$2o2b3o!";
+
+/// Initial state of a benchmark.
+struct Workload {
+    name: &'static str,
+    grid: DoubleBufferGrid,
+}
+
+impl Workload {
+    fn random(name: &'static str, size: usize, density: f64) -> Self {
+        let mut grid = DoubleBufferGrid::new(size, size);
+        grid.randomize(1, density);
+        Self { name, grid }
+    }
+
+    fn pattern(name: &'static str, size: usize, rle: &str) -> Self {
+        let pattern = rle::read(rle).expect("Failed to read a benchmark pattern");
+        let mut grid = DoubleBufferGrid::new(size, size);
+        grid.load_pattern(&pattern, None);
+        Self { name, grid }
+    }
+
+    fn dense_grid(&self) -> DoubleBufferGrid {
+        let mut grid = DoubleBufferGrid::new(self.grid.width(), self.grid.height());
+        for ((x, y), state) in self.grid.cells() {
+            grid.set_cell(x as usize, y as usize, state);
+        }
+        grid
+    }
+
+    fn packed_grid(&self) -> PackedGrid {
+        let mut grid = PackedGrid::new(self.grid.width(), self.grid.height(), false);
+        for ((x, y), state) in self.grid.cells() {
+            grid.set_cell(x as usize, y as usize, state);
+        }
+        grid
+    }
+}
+
+fn workloads() -> Vec<Workload> {
+    vec![
+        Workload::random("random_soup", 256, 0.375),
+        Workload::pattern("gosper_glider_gun", 128, GOSPER_GLIDER_GUN),
+        Workload::pattern("acorn", 512, ACORN),
+        Workload::random("sparse_512", 512, 0.02),
+        Workload::random("dense_512", 512, 0.5),
+    ]
+}
+
+fn step(c: &mut Criterion) {
+    let rule = Rule::default();
+
+    for workload in workloads() {
+        let mut group = c.benchmark_group(workload.name);
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(GENERATIONS));
+
+        for policy in [BorderPolicy::Clamp, BorderPolicy::Wrap] {
+            let id = BenchmarkId::new("double_buffer_grid", format!("{:?}", policy));
+            group.bench_function(id, |b| {
+                b.iter_batched(
+                    || workload.dense_grid(),
+                    |mut grid| {
+                        for _ in 0..GENERATIONS {
+                            grid.step(&rule, policy.clone());
+                        }
+                        grid
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+
+            let id = BenchmarkId::new("packed_grid", format!("{:?}", policy));
+            group.bench_function(id, |b| {
+                b.iter_batched(
+                    || workload.packed_grid(),
+                    |mut grid| {
+                        for _ in 0..GENERATIONS {
+                            grid.step(&rule, policy.clone());
+                        }
+                        grid
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+use crate::{border_policy::BorderPolicy, rule::Rule};
+
+/// Tiles of a bounded grid computed on the next step. A tile is active when a
+/// tile within reach of the neighbourhood changed since the previous step, the
+/// other tiles keeping their cells as nothing they depend on changed.
+#[derive(Debug, Clone)]
+pub struct ActiveTiles {
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    rows: usize,
+    active: Vec<bool>,
+    /// Tiles around a changed one that depend on it, along each axis.
+    reach: (usize, usize),
+    wrap: bool,
+    /// Rule and border policy of the last step, tiles being stable only under them.
+    last_step: Option<(Rule, BorderPolicy)>,
+}
+
+impl ActiveTiles {
+    /// Tiles covering a grid of the given size, all active.
+    pub fn new(width: usize, height: usize, tile_width: usize, tile_height: usize) -> Self {
+        let columns = width.div_ceil(tile_width);
+        let rows = height.div_ceil(tile_height);
+        Self {
+            tile_width,
+            tile_height,
+            columns,
+            rows,
+            active: vec![true; columns * rows],
+            reach: (1, 1),
+            wrap: false,
+            last_step: None,
+        }
+    }
+
+    pub fn tile_width(&self) -> usize {
+        self.tile_width
+    }
+
+    pub fn tile_height(&self) -> usize {
+        self.tile_height
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Amount of active tiles.
+    pub fn count(&self) -> usize {
+        self.active.iter().filter(|active| **active).count()
+    }
+
+    pub fn is_tile_active(&self, column: usize, row: usize) -> bool {
+        self.active[row * self.columns + column]
+    }
+
+    /// Whether the tile holding a cell is active.
+    pub fn is_active(&self, x: usize, y: usize) -> bool {
+        self.is_tile_active(x / self.tile_width, y / self.tile_height)
+    }
+
+    pub fn mark_all(&mut self) {
+        self.active.fill(true);
+    }
+
+    /// Activates the tiles depending on a cell that was edited.
+    pub fn mark_cell(&mut self, x: usize, y: usize) {
+        self.activate_around(x / self.tile_width, y / self.tile_height);
+    }
+
+    /// Prepares a step, activating every tile when the rule or border policy
+    /// differs from the last step.
+    pub fn start_step(&mut self, rule: &Rule, policy: &BorderPolicy) {
+        if self.last_step.as_ref() == Some(&(rule.clone(), policy.clone())) {
+            return;
+        }
+
+        let radius = rule
+            .neighbour_offsets()
+            .iter()
+            .map(|(dx, dy)| dx.unsigned_abs().max(dy.unsigned_abs()) as usize)
+            .max()
+            .unwrap_or(1);
+        self.reach = (
+            radius.div_ceil(self.tile_width).max(1),
+            radius.div_ceil(self.tile_height).max(1),
+        );
+        self.wrap = *policy == BorderPolicy::Wrap;
+        self.last_step = Some((rule.clone(), policy.clone()));
+        self.mark_all();
+    }
+
+    /// Activates the tiles depending on the ones the step changed, row by row.
+    pub fn finish_step(&mut self, changed: &[bool]) {
+        self.active.fill(false);
+        for (index, _) in changed.iter().enumerate().filter(|(_, changed)| **changed) {
+            self.activate_around(index % self.columns, index / self.columns);
+        }
+    }
+
+    fn activate_around(&mut self, column: usize, row: usize) {
+        let (reach_x, reach_y) = (self.reach.0 as isize, self.reach.1 as isize);
+        for dy in -reach_y..=reach_y {
+            for dx in -reach_x..=reach_x {
+                let (x, y) = (column as isize + dx, row as isize + dy);
+                let (x, y) = match self.wrap {
+                    true => (
+                        x.rem_euclid(self.columns as isize),
+                        y.rem_euclid(self.rows as isize),
+                    ),
+                    false => (x, y),
+                };
+                if x >= 0 && y >= 0 && x < self.columns as isize && y < self.rows as isize {
+                    self.active[y as usize * self.columns + x as usize] = true;
+                }
+            }
+        }
+    }
+}
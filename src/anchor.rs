@@ -0,0 +1,30 @@
+use std::str::FromStr;
+
+/// Point of the universe that stays in place when it is resized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Centre,
+}
+
+impl Anchor {
+    /// Offset to add to the cell coordinates when a dimension goes from `from` to `to`.
+    pub fn offset(&self, from: usize, to: usize) -> isize {
+        match self {
+            Self::TopLeft => 0,
+            Self::Centre => (to as isize - from as isize) / 2,
+        }
+    }
+}
+
+impl FromStr for Anchor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "top-left" | "topleft" => Ok(Self::TopLeft),
+            "centre" | "center" => Ok(Self::Centre),
+            _ => anyhow::bail!("Unknown anchor '{}', expected top-left or centre", s),
+        }
+    }
+}
@@ -1,57 +1,488 @@
+use std::path::{Path, PathBuf};
+
 use ratatui::prelude::Rect;
 
+use conways_tui::{
+    anchor::Anchor,
+    cell_state::CellState,
+    hashlife::HashLife,
+    pattern::Pattern,
+    rule::{Rule, RULE_PRESETS},
+    Simulation,
+};
+
 use crate::{
-    app_layout::AppLayout, border_policy::BorderPolicy, cell_state::CellState,
-    double_buffer_grid::DoubleBufferGrid, player_state::PlayerState,
+    app_layout::AppLayout,
+    drawing, edit_mode::EditMode, player_state::PlayerState, speed::Speed,
+    zoom::Zoom,
 };
 
+/// Milliseconds between updates unless configured otherwise.
+const DEFAULT_UPDATE_MS: u64 = 75;
+
+/// Largest jump of hyperspeed, as a power of two.
+const MAX_STEP_EXPONENT: u32 = 32;
+
+/// Largest typed step count, stepped at once without blocking the interface for long.
+const MAX_STEP_COUNT: usize = 10_000;
+
 pub struct App {
-    pub grids: DoubleBufferGrid,
-    pub grid_width: usize,
-    pub grid_height: usize,
-    pub cycle_count: usize,
-    pub border_policy: BorderPolicy,
+    pub simulation: Simulation,
+    /// Seed of the last random fill.
+    pub seed: u64,
+    /// Ratio of alive cells of random fills.
+    pub density: f64,
     pub layout: AppLayout,
+    /// Universe cell shown at the top left corner of the grid panel.
+    pub viewport: (i64, i64),
+    /// Terminal position and viewport when the view started being dragged.
+    pub pan_start: Option<((u16, u16), (i64, i64))>,
+    /// Keeps the view centred on the population as it moves.
+    pub follow_population: bool,
+    /// Tints the tiles the next step computes.
+    pub show_active_tiles: bool,
     pub player_state: PlayerState,
+    pub speed: Speed,
+    /// Generations computed on each update in hyperspeed, as a power of two.
+    pub step_exponent: u32,
+    /// Messages shown in the console panel, oldest first.
+    pub console: Vec<String>,
+    /// Number typed before a step command, the amount of generations to step.
+    pub step_count: Option<usize>,
+    pub edit_mode: EditMode,
+    /// Grid cell the keyboard edits, shown while editing.
+    pub cursor: (i64, i64),
+    /// Last cell painted while dragging the mouse, to fill the gaps between drag events.
+    pub last_painted_cell: Option<(i64, i64)>,
+    /// Whether the universe is resized along with the terminal.
+    pub follow_terminal: bool,
+    /// Point of the universe kept in place when the terminal is resized.
+    pub resize_anchor: Anchor,
+    /// Only grow the universe on resize, a smaller terminal just crops the view.
+    pub never_shrink: bool,
     pub should_quit: bool,
 }
 
 impl App {
-    pub fn new(terminal_rect: Rect) -> Self {
-        let width = terminal_rect.width as usize;
-        let height = terminal_rect.height as usize;
-
-        let layout = AppLayout::generate(terminal_rect);
-        let grids = DoubleBufferGrid::new(width, height);
+    /// Creates the app with a grid of the given size, or sized to the terminal.
+    pub fn new(terminal_rect: Rect, grid_size: Option<(usize, usize)>) -> Self {
+        let layout = AppLayout::generate(terminal_rect, Zoom::default());
+        let (width, height) = grid_size.unwrap_or(Self::grid_size_for(&layout));
 
         App {
-            grids,
-            grid_width: width,
-            grid_height: height,
-            cycle_count: 0,
-            border_policy: BorderPolicy::Clamp,
+            simulation: Simulation::new(width, height),
+            seed: 0,
+            density: 0.5,
             layout,
+            viewport: (0, 0),
+            pan_start: None,
+            follow_population: false,
+            show_active_tiles: false,
             player_state: PlayerState::Pause,
+            speed: Speed::new(DEFAULT_UPDATE_MS),
+            step_exponent: 4,
+            console: Vec::new(),
+            step_count: None,
+            edit_mode: EditMode::Off,
+            cursor: (0, 0),
+            last_painted_cell: None,
+            follow_terminal: grid_size.is_none(),
+            resize_anchor: Anchor::Centre,
+            never_shrink: false,
             should_quit: false,
         }
     }
 
+    /// Grid size filling the grid panel of a layout.
+    fn grid_size_for(layout: &AppLayout) -> (usize, usize) {
+        (layout.width().max(1), layout.height().max(1))
+    }
+
+    /// Changes how many cells each character shows, keeping the middle of the view in place.
+    pub fn set_zoom(&mut self, zoom: Zoom) {
+        let centre = (
+            self.viewport.0 + (self.layout.width() / 2) as i64,
+            self.viewport.1 + (self.layout.height() / 2) as i64,
+        );
+        self.layout = AppLayout::generate(self.layout.main_layout, zoom);
+        self.centre_view_on(centre.0, centre.1);
+    }
+
+    pub fn zoom_in(&mut self) {
+        let mut zoom = self.layout.zoom;
+        zoom.zoom_in();
+        self.set_zoom(zoom);
+    }
+
+    pub fn zoom_out(&mut self) {
+        let mut zoom = self.layout.zoom;
+        zoom.zoom_out();
+        self.set_zoom(zoom);
+    }
+
+    /// Lays the panels out again for a new terminal size, resizing the
+    /// universe too unless its size was given. The universe follows the
+    /// terminal at the default zoom, whatever the current one.
+    pub fn resize(&mut self, terminal_rect: Rect) {
+        self.layout = AppLayout::generate(terminal_rect, self.layout.zoom);
+        self.last_painted_cell = None;
+
+        if self.follow_terminal {
+            let layout = AppLayout::generate(terminal_rect, Zoom::default());
+            let (mut width, mut height) = Self::grid_size_for(&layout);
+            if self.never_shrink {
+                width = width.max(self.simulation.width());
+                height = height.max(self.simulation.height());
+            }
+            if (width, height) != (self.simulation.width(), self.simulation.height()) {
+                self.simulation.resize(width, height, self.resize_anchor);
+                self.log(format!("Resized the grid to {}x{}", width, height));
+            }
+        }
+
+        self.pan(0, 0);
+        if self.edit_mode != EditMode::Off {
+            self.move_cursor(0, 0);
+        }
+    }
+
+    /// Size of the part of the universe shown in the grid panel.
+    pub fn view_size(&self) -> (usize, usize) {
+        if !self.simulation.is_bounded() {
+            return (self.layout.width(), self.layout.height());
+        }
+        (
+            self.layout.width().min(self.simulation.width()),
+            self.layout.height().min(self.simulation.height()),
+        )
+    }
+
+    /// Moves the view, keeping it inside a bounded universe.
+    pub fn pan(&mut self, dx: i64, dy: i64) {
+        let (x, y) = (self.viewport.0 + dx, self.viewport.1 + dy);
+        if !self.simulation.is_bounded() {
+            self.viewport = (x, y);
+            return;
+        }
+
+        let max_x = self.simulation.width().saturating_sub(self.layout.width());
+        let max_y = self.simulation.height().saturating_sub(self.layout.height());
+        self.viewport = (x.clamp(0, max_x as i64), y.clamp(0, max_y as i64));
+    }
+
+    /// Moves the view so the given universe cell is in its middle, as far as
+    /// the universe edges allow.
+    pub fn centre_view_on(&mut self, x: i64, y: i64) {
+        self.viewport = (
+            x - (self.layout.width() / 2) as i64,
+            y - (self.layout.height() / 2) as i64,
+        );
+        self.pan(0, 0);
+    }
+
+    /// Centres the view on the bounding box of the alive cells, if any.
+    pub fn centre_on_population(&mut self) {
+        if let Some((x, y, width, height)) = self.simulation.bounding_box() {
+            self.centre_view_on(x + (width / 2) as i64, y + (height / 2) as i64);
+        }
+    }
+
+    pub fn switch_follow_population(&mut self) {
+        self.follow_population = !self.follow_population;
+        if self.follow_population {
+            self.centre_on_population();
+        }
+    }
+
+    /// Universe cell under a terminal position, if the position shows one.
+    pub fn cell_at(&self, column: u16, row: u16) -> Option<(i64, i64)> {
+        let (x, y) = self.layout.grid_cell_at(column, row)?;
+        let (x, y) = (self.viewport.0 + x as i64, self.viewport.1 + y as i64);
+        self.simulation.get_cell(x, y)?;
+        Some((x, y))
+    }
+
+    /// Starts dragging the view from a terminal position.
+    pub fn start_pan(&mut self, column: u16, row: u16) {
+        self.pan_start = Some(((column, row), self.viewport));
+    }
+
+    /// Drags the view so the cell under the start position follows the pointer.
+    pub fn pan_to(&mut self, column: u16, row: u16) {
+        let Some(((start_column, start_row), viewport)) = self.pan_start else {
+            return;
+        };
+
+        let (dx, dy) = self
+            .layout
+            .cells_in(start_column as i64 - column as i64, start_row as i64 - row as i64);
+        self.viewport = viewport;
+        self.pan(dx, dy);
+    }
+
+    pub fn end_pan(&mut self) {
+        self.pan_start = None;
+    }
+
+    /// Enters or leaves the edit mode, bringing the cursor into view when entering.
+    pub fn switch_edit_mode(&mut self) {
+        self.edit_mode.switch();
+        if self.edit_mode == EditMode::Off {
+            return;
+        }
+
+        let (width, height) = self.view_size();
+        let (x, y) = self.viewport;
+        self.cursor = (
+            self.cursor.0.clamp(x, x + width.saturating_sub(1) as i64),
+            self.cursor.1.clamp(y, y + height.saturating_sub(1) as i64),
+        );
+    }
+
+    /// Randomizes the grid with a new seed.
     pub fn randomize_cells(&mut self) -> &mut Self {
-        self.grids.randomize();
+        self.randomize_cells_with_seed(rand::random())
+    }
+
+    pub fn randomize_cells_with_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self.simulation.randomize(seed, self.density);
         self
     }
 
-    pub fn get_alive_neighbours_at_point(&self, x: usize, y: usize) -> usize {
-        self.grids
-            .get_alive_neighbours_at_point(x, y, self.border_policy.clone())
+    /// Randomizes the grid again with the current seed, replaying the run from its start.
+    pub fn replay_seed(&mut self) -> &mut Self {
+        self.randomize_cells_with_seed(self.seed)
+    }
+
+    pub fn change_density(&mut self, delta: f64) {
+        self.density = (self.density + delta).clamp(0.0, 1.0);
+    }
+
+    /// Loads a pattern and centres the view on it.
+    pub fn load_pattern(&mut self, pattern: &Pattern, offset: Option<(usize, usize)>) {
+        self.simulation.load_pattern(pattern, offset);
+        self.centre_on_population();
+    }
+
+    /// Saves the current generation as an RLE file in the working directory,
+    /// next to the earlier snapshots, reporting the outcome in the console.
+    pub fn save_snapshot(&mut self) {
+        let path = snapshot_path(Path::new(""), self.simulation.generation());
+        // RLE snapshots only hold alive and dead cells.
+        let dying = match self.simulation.rule.dying_states() {
+            0 => "",
+            _ => ", dying cells saved as dead",
+        };
+        match self.simulation.save(&path) {
+            Ok(()) => self.log(format!("Saved {}{}", path.display(), dying)),
+            Err(e) => self.log(format!("Failed to save {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Moves the cursor inside the universe, scrolling the view to keep it visible.
+    pub fn move_cursor(&mut self, dx: i64, dy: i64) {
+        let (mut x, mut y) = (self.cursor.0 + dx, self.cursor.1 + dy);
+        if self.simulation.is_bounded() {
+            x = x.clamp(0, self.simulation.width().saturating_sub(1) as i64);
+            y = y.clamp(0, self.simulation.height().saturating_sub(1) as i64);
+        }
+        self.cursor = (x, y);
+
+        let (view_width, view_height) = self.view_size();
+        let scroll = |cursor: i64, start: i64, size: usize| {
+            if cursor < start {
+                cursor - start
+            } else if cursor >= start + size as i64 {
+                cursor + 1 - size as i64 - start
+            } else {
+                0
+            }
+        };
+        let dx = scroll(self.cursor.0, self.viewport.0, view_width.max(1));
+        let dy = scroll(self.cursor.1, self.viewport.1, view_height.max(1));
+        self.pan(dx, dy);
+    }
+
+    pub fn toggle_cell_at_cursor(&mut self) {
+        let (x, y) = self.cursor;
+        let state = match self.simulation.get_cell(x, y) {
+            Some(CellState::Alive(_)) => CellState::Dead,
+            _ => CellState::Alive(0),
+        };
+        self.simulation.set_cell(x, y, state);
+        self.simulation.commit_edit();
+    }
+
+    /// Cells the pending line or rectangle would change once confirmed.
+    pub fn edit_preview(&self) -> Vec<(i64, i64)> {
+        match self.edit_mode {
+            EditMode::Line { anchor } => drawing::line(anchor, self.cursor),
+            EditMode::Rectangle { anchor } => drawing::rectangle(anchor, self.cursor),
+            EditMode::Off | EditMode::Cursor => Vec::new(),
+        }
+    }
+
+    /// Applies the pending line or rectangle, back to moving the cursor freely.
+    pub fn confirm_edit(&mut self, alive: bool) {
+        for (x, y) in self.edit_preview() {
+            let state = if alive {
+                CellState::Alive(0)
+            } else {
+                CellState::Dead
+            };
+            self.simulation.set_cell(x, y, state);
+        }
+        self.simulation.commit_edit();
+        self.edit_mode = EditMode::Cursor;
+    }
+
+    /// Sets every cell between the last painted cell and the given one alive or dead.
+    pub fn paint_to(&mut self, cell: (i64, i64), alive: bool) {
+        let from = self.last_painted_cell.unwrap_or(cell);
+        for (x, y) in drawing::line(from, cell) {
+            let state = if alive {
+                CellState::Alive(0)
+            } else {
+                CellState::Dead
+            };
+            self.simulation.set_cell(x, y, state);
+        }
+        self.last_painted_cell = Some(cell);
+    }
+
+    /// Ends the current mouse stroke, so it is undone at once.
+    pub fn end_stroke(&mut self) {
+        self.last_painted_cell = None;
+        self.simulation.commit_edit();
+    }
+
+    /// Goes back the given amount of generations or edits, pausing the simulation
+    /// so it does not move forward again right away.
+    pub fn undo(&mut self, steps: usize) {
+        self.player_state = PlayerState::Pause;
+        for _ in 0..steps {
+            if !self.simulation.undo() {
+                break;
+            }
+        }
+    }
+
+    pub fn redo(&mut self, steps: usize) {
+        for _ in 0..steps {
+            if !self.simulation.redo() {
+                break;
+            }
+        }
+    }
+
+    pub fn log(&mut self, message: String) {
+        self.console.push(message);
+    }
+
+    pub fn next_rule(&mut self) {
+        let index = self.simulation.rule.preset_index().map_or(0, |i| i + 1);
+        self.set_rule(Rule::from_preset(index));
+    }
+
+    pub fn previous_rule(&mut self) {
+        let count = RULE_PRESETS.len();
+        let index = self
+            .simulation
+            .rule
+            .preset_index()
+            .map_or(0, |i| i + count - 1);
+        self.set_rule(Rule::from_preset(index));
+    }
+
+    /// Changes the rule, leaving hyperspeed when HashLife does not support it.
+    fn set_rule(&mut self, rule: Rule) {
+        self.simulation.rule = rule;
+        if self.simulation.is_hyperspeed() && !HashLife::supports(&self.simulation.rule) {
+            let _ = self.simulation.set_hyperspeed(false);
+            self.log(format!("Hyperspeed off, not supported by {}", self.simulation.rule));
+        }
+    }
+
+    /// Appends a digit to the typed step count, up to [`MAX_STEP_COUNT`].
+    pub fn type_step_digit(&mut self, digit: usize) {
+        let count = self.step_count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+        self.step_count = Some(count.min(MAX_STEP_COUNT));
+    }
+
+    pub fn erase_step_digit(&mut self) {
+        self.step_count = self.step_count.map(|count| count / 10).filter(|count| *count > 0);
+    }
+
+    /// Advances the typed amount of generations, or a single one, pausing the
+    /// simulation first when it is playing.
+    pub fn step(&mut self) {
+        self.player_state = PlayerState::Pause;
+        let generations = self.step_count.take().unwrap_or(1);
+        self.simulation.step_n(generations);
     }
 
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
 
+    /// Updates the simulation when the update interval has passed.
     pub fn tick(&mut self) {
-        self.logic_update().expect("Failed to do logic update on tick");
+        if self.speed.is_due() {
+            self.logic_update().expect("Failed to do logic update on tick");
+        }
+        if self.follow_population {
+            self.centre_on_population();
+        }
+    }
+
+    /// Moves to the next border policy, from clamped to wrapped to unbounded.
+    pub fn switch_border_policy(&mut self) {
+        let mut policy = self.simulation.border_policy().clone();
+        policy.switch();
+        self.simulation.set_border_policy(policy);
+        self.pan(0, 0);
+        if self.edit_mode != EditMode::Off {
+            self.move_cursor(0, 0);
+        }
+    }
+
+    /// Switches HashLife hyperspeed on or off, which makes the universe unbounded.
+    pub fn switch_hyperspeed(&mut self) {
+        let hyperspeed = !self.simulation.is_hyperspeed();
+        match self.simulation.set_hyperspeed(hyperspeed) {
+            Ok(()) if hyperspeed => self.log("Hyperspeed on".to_string()),
+            Ok(()) => self.log("Hyperspeed off".to_string()),
+            Err(e) => self.log(e.to_string()),
+        }
+        self.pan(0, 0);
+        if self.edit_mode != EditMode::Off {
+            self.move_cursor(0, 0);
+        }
+    }
+
+    /// Generations computed on each update.
+    pub fn generations_per_update(&self) -> usize {
+        match self.simulation.is_hyperspeed() {
+            true => 1 << self.step_exponent,
+            false => self.speed.generations_per_update,
+        }
+    }
+
+    /// Doubles the generations computed on each update.
+    pub fn more_per_update(&mut self) {
+        match self.simulation.is_hyperspeed() {
+            true => self.step_exponent = (self.step_exponent + 1).min(MAX_STEP_EXPONENT),
+            false => self.speed.more_per_update(),
+        }
+    }
+
+    /// Halves the generations computed on each update.
+    pub fn fewer_per_update(&mut self) {
+        match self.simulation.is_hyperspeed() {
+            true => self.step_exponent = self.step_exponent.saturating_sub(1),
+            false => self.speed.fewer_per_update(),
+        }
     }
 
     pub fn logic_update(&mut self) -> anyhow::Result<()> {
@@ -60,35 +491,122 @@ impl App {
             PlayerState::Pause => return Ok(()),
         };
 
-        self.cycle_count += 1;
-        self.grids.add_cycle();
-
-        for y in 0..self.grid_height {
-            for x in 0..self.grid_width {
-                let index = (y * self.grid_width + x) as usize;
-                let alive_neighbours = self.get_alive_neighbours_at_point(x, y);
-                let cell = &self.grids.get_read_grid()[index].clone();
-
-                let write = self.grids.get_write_grid();
-                match cell {
-                    CellState::Dead => {
-                        write[index] = if alive_neighbours == 3 {
-                            CellState::Alive(0)
-                        } else {
-                            CellState::Dead
-                        };
-                    }
-                    CellState::Alive(c) => {
-                        write[index] = if alive_neighbours == 2 || alive_neighbours == 3 {
-                            CellState::Alive(c + 1)
-                        } else {
-                            CellState::Dead
-                        };
-                    }
-                }
-            }
+        let generations = self.generations_per_update();
+        match self.simulation.is_hyperspeed() {
+            true => self.simulation.step_pow2(self.step_exponent)?,
+            false => self.simulation.step_n(generations),
         }
+        self.speed.record(generations);
 
         Ok(())
     }
 }
+
+/// First snapshot path of the generation not taken yet in the directory,
+/// e.g. `snapshot_gen12_2.rle` for the second snapshot of generation 12.
+fn snapshot_path(directory: &Path, generation: usize) -> PathBuf {
+    (1..)
+        .map(|n| match n {
+            1 => format!("snapshot_gen{}.rle", generation),
+            n => format!("snapshot_gen{}_{}.rle", generation, n),
+        })
+        .map(|name| directory.join(name))
+        .find(|path| !path.exists())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLINKER: [(i64, i64); 3] = [(7, 8), (8, 8), (9, 8)];
+
+    fn app_with_blinker() -> App {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        for (x, y) in BLINKER {
+            app.simulation.set_cell(x, y, CellState::Alive(0));
+        }
+        app
+    }
+
+    fn alive_cells(app: &App) -> Vec<(i64, i64)> {
+        let mut cells = app.simulation.alive_cells_in(0, 0, 16, 16);
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn paused_update_does_nothing() {
+        let mut app = app_with_blinker();
+        app.logic_update().unwrap();
+        assert_eq!(app.simulation.generation(), 0);
+        assert_eq!(alive_cells(&app), BLINKER.to_vec());
+    }
+
+    #[test]
+    fn update_advances_the_generations_per_update() {
+        let mut app = app_with_blinker();
+        app.player_state = PlayerState::Play;
+
+        app.logic_update().unwrap();
+        assert_eq!(app.simulation.generation(), 1);
+        assert_eq!(alive_cells(&app), vec![(8, 7), (8, 8), (8, 9)]);
+
+        app.more_per_update();
+        app.logic_update().unwrap();
+        assert_eq!(app.simulation.generation(), 3);
+        assert_eq!(alive_cells(&app), vec![(8, 7), (8, 8), (8, 9)]);
+    }
+
+    #[test]
+    fn hyperspeed_update_advances_a_power_of_two() {
+        let mut app = app_with_blinker();
+        app.player_state = PlayerState::Play;
+        app.switch_hyperspeed();
+        app.step_exponent = 3;
+
+        app.logic_update().unwrap();
+        assert_eq!(app.simulation.generation(), 8);
+        assert_eq!(alive_cells(&app), BLINKER.to_vec());
+    }
+
+    #[test]
+    fn typed_step_count_is_capped() {
+        let mut app = app_with_blinker();
+        for _ in 0..30 {
+            app.type_step_digit(9);
+        }
+        assert_eq!(app.step_count, Some(MAX_STEP_COUNT));
+    }
+
+    #[test]
+    fn unsupported_rule_leaves_hyperspeed() {
+        let mut app = app_with_blinker();
+        app.switch_hyperspeed();
+        assert!(app.simulation.is_hyperspeed());
+
+        while HashLife::supports(&app.simulation.rule) {
+            app.next_rule();
+        }
+        assert!(!app.simulation.is_hyperspeed());
+        assert!(app.simulation.step_pow2(MAX_STEP_EXPONENT).is_err());
+        assert_eq!(app.simulation.generation(), 0);
+    }
+
+    #[test]
+    fn snapshots_do_not_overwrite_each_other() {
+        let directory = std::env::temp_dir().join(format!("conways_tui_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let first = snapshot_path(&directory, 0);
+        assert_eq!(first, directory.join("snapshot_gen0.rle"));
+        std::fs::write(&first, "").unwrap();
+        let second = snapshot_path(&directory, 0);
+        assert_eq!(second, directory.join("snapshot_gen0_2.rle"));
+        std::fs::write(&second, "").unwrap();
+        assert_eq!(snapshot_path(&directory, 0), directory.join("snapshot_gen0_3.rle"));
+        assert_eq!(snapshot_path(&directory, 1), directory.join("snapshot_gen1.rle"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
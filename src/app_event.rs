@@ -8,8 +8,6 @@ use std::time::{Duration, Instant};
 pub enum Event {
     /// None
     None,
-    /// Terminal tick.
-    Tick,
     /// Key press.
     Key(KeyEvent),
     /// Mouse click/scroll.
@@ -19,15 +17,10 @@ pub enum Event {
 }
 
 /// Terminal event handler.
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct EventHandler {
-    /// Event sender channel.
-    sender: mpsc::Sender<Event>,
     /// Event receiver channel.
     receiver: mpsc::Receiver<Event>,
-    /// Event handler thread.
-    handler: thread::JoinHandle<()>,
 }
 
 impl EventHandler {
@@ -35,60 +28,30 @@ impl EventHandler {
     pub fn new_input_event_handler(tick_rate: u64) -> Self {
         let tick_rate = Duration::from_millis(tick_rate);
         let (sender, receiver) = mpsc::channel();
-        let handler = {
-            let sender = sender.clone();
-            thread::spawn(move || {
-                let mut last_tick = Instant::now();
-                loop {
-                    let timeout = tick_rate
-                        .checked_sub(last_tick.elapsed())
-                        .unwrap_or(tick_rate);
+        thread::spawn(move || {
+            let mut last_tick = Instant::now();
+            loop {
+                let timeout = tick_rate
+                    .checked_sub(last_tick.elapsed())
+                    .unwrap_or(tick_rate);
 
-                    if event::poll(timeout).expect("no events available") {
-                        match event::read().expect("unable to read event") {
-                            CrosstermEvent::Key(e) => sender.send(Event::Key(e)),
-                            CrosstermEvent::Mouse(e) => sender.send(Event::Mouse(e)),
-                            CrosstermEvent::Resize(w, h) => sender.send(Event::Resize(w, h)),
-                            _ => Ok(()),
-                        }
-                        .expect("failed to send terminal event")
-                    }
-
-                    if last_tick.elapsed() >= tick_rate {
-                        last_tick = Instant::now();
+                if event::poll(timeout).expect("no events available") {
+                    match event::read().expect("unable to read event") {
+                        CrosstermEvent::Key(e) => sender.send(Event::Key(e)),
+                        CrosstermEvent::Mouse(e) => sender.send(Event::Mouse(e)),
+                        CrosstermEvent::Resize(w, h) => sender.send(Event::Resize(w, h)),
+                        _ => Ok(()),
                     }
+                    .expect("failed to send terminal event")
                 }
-            })
-        };
-
-        Self {
-            sender,
-            receiver,
-            handler,
-        }
-    }
 
-    pub fn new_update_event_handler(tick_rate: u64) -> Self {
-        let tick_rate = Duration::from_millis(tick_rate);
-        let (sender, receiver) = mpsc::channel();
-        let handler = {
-            let sender = sender.clone();
-            thread::spawn(move || {
-                let mut last_tick = Instant::now();
-                loop {
-                    if last_tick.elapsed() >= tick_rate {
-                        sender.send(Event::Tick).expect("failed to send tick event");
-                        last_tick = Instant::now();
-                    }
+                if last_tick.elapsed() >= tick_rate {
+                    last_tick = Instant::now();
                 }
-            })
-        };
+            }
+        });
 
-        Self {
-            sender,
-            receiver,
-            handler,
-        }
+        Self { receiver }
     }
 
     /// Receive the next event from the handler thread.
@@ -1,12 +1,20 @@
 use ratatui::prelude::{Rect, Constraint, Layout, Direction, Margin};
 
+use crate::zoom::Zoom;
+
+/// Columns of the Config and Console panels, enough for most Config lines.
+const SIDE_PANEL_WIDTH: u16 = 34;
+
+/// Rows of the Cheatsheet panel, four lines of bindings and its border.
+const CHEATSHEET_HEIGHT: u16 = 6;
+
 pub struct AppLayout {
     pub main_layout: Rect,
     pub grid_panel: Rect,
     pub config_panel: Rect,
     pub console_panel: Rect,
     pub bottom_panel: Rect,
-    pub grid_constraints: Vec<Constraint>,
+    pub zoom: Zoom,
     grid_cell_width: usize,
     grid_cell_height: usize,
 }
@@ -20,17 +28,18 @@ impl AppLayout {
         self.grid_cell_height
     }
 
-    pub fn generate(terminal_rect: Rect) -> Self {
+    /// Lays the panels out, the grid size in cells depending on the zoom.
+    pub fn generate(terminal_rect: Rect, zoom: Zoom) -> Self {
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(10), Constraint::Length(3)])
+            .constraints([Constraint::Min(10), Constraint::Length(CHEATSHEET_HEIGHT)])
             .split(terminal_rect.inner(&Margin::new(1, 1)));
 
         let bottom_panel = main_layout[1];
 
         let main_layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+            .constraints([Constraint::Min(0), Constraint::Length(SIDE_PANEL_WIDTH)])
             .split(main_layout[0]);
 
         let right_layout = Layout::default()
@@ -38,11 +47,9 @@ impl AppLayout {
             .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
             .split(main_layout[1]);
 
-        let grid_width = Self::get_grid_width(&main_layout[0]);
-        let grid_height = Self::get_grid_height(&main_layout[0]);
-        let constrains = std::iter::repeat(Constraint::Length(2))
-            .take(grid_width as usize)
-            .collect::<Vec<Constraint>>();
+        let (cells_x, cells_y) = zoom.cells_per_char();
+        let grid_width = Self::get_grid_width(&main_layout[0], zoom) * cells_x;
+        let grid_height = Self::get_grid_height(&main_layout[0]) * cells_y;
 
         Self {
             main_layout: terminal_rect,
@@ -50,17 +57,49 @@ impl AppLayout {
             config_panel: right_layout[0],
             console_panel: right_layout[1],
             bottom_panel,
-            grid_constraints: constrains,
+            zoom,
             grid_cell_width: grid_width,
             grid_cell_height: grid_height,
         }
     }
 
-    fn get_grid_width(grid_panel: &Rect) -> usize {
-        ((grid_panel.width - 2) / 2) as usize
+    /// Grid cell under a terminal position, if the position is inside the grid panel.
+    /// When a character shows several cells, its top left one is picked.
+    /// The panel has a one cell border.
+    pub fn grid_cell_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let inner = self.grid_panel.inner(&Margin::new(1, 1));
+        if column < inner.x || row < inner.y {
+            return None;
+        }
+
+        let (x, y) = self.cells_in((column - inner.x) as i64, (row - inner.y) as i64);
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.grid_cell_width || y >= self.grid_cell_height {
+            return None;
+        }
+        Some((x, y))
+    }
+
+    /// Amount of cells spanned by a distance in terminal columns and rows.
+    pub fn cells_in(&self, columns: i64, rows: i64) -> (i64, i64) {
+        let (cells_x, cells_y) = self.zoom.cells_per_char();
+        (
+            columns / self.zoom.columns_per_char() as i64 * cells_x as i64,
+            rows * cells_y as i64,
+        )
+    }
+
+    /// Amount of cells spanned by a distance in characters of the grid.
+    pub fn cells_in_chars(&self, chars_x: i64, chars_y: i64) -> (i64, i64) {
+        let (cells_x, cells_y) = self.zoom.cells_per_char();
+        (chars_x * cells_x as i64, chars_y * cells_y as i64)
+    }
+
+    fn get_grid_width(grid_panel: &Rect, zoom: Zoom) -> usize {
+        grid_panel.width.saturating_sub(2) as usize / zoom.columns_per_char()
     }
 
     fn get_grid_height(grid_panel: &Rect) -> usize {
-        (grid_panel.height - 2) as usize
+        grid_panel.height.saturating_sub(2) as usize
     }
 }
@@ -1,14 +1,35 @@
+use std::str::FromStr;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum BorderPolicy {
     Clamp,
     Wrap,
+    /// No edges, the universe grows as the patterns expand.
+    Unbounded,
 }
 
 impl BorderPolicy {
     pub fn switch(&mut self) {
         *self = match self {
             Self::Clamp => Self::Wrap,
-            Self::Wrap => Self::Clamp,
+            Self::Wrap => Self::Unbounded,
+            Self::Unbounded => Self::Clamp,
         };
     }
 }
+
+impl FromStr for BorderPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(Self::Clamp),
+            "wrap" => Ok(Self::Wrap),
+            "unbounded" | "infinite" => Ok(Self::Unbounded),
+            _ => anyhow::bail!(
+                "Unknown border policy '{}', expected clamp, wrap or unbounded",
+                s
+            ),
+        }
+    }
+}
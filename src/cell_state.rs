@@ -1,5 +1,7 @@
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum CellState {
     Alive(usize),
+    /// Generations rules only, the step of the dying sequence the cell is in.
+    Dying(usize),
     Dead,
 }
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use conways_tui::{anchor::Anchor, border_policy::BorderPolicy, rule::Rule};
+
+/// Conway's Game of Life, and other Life-like rules, in the terminal.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Rule to run, e.g. B3/S23, 23/3, B2/S/C3 or R5,C0,M1,S34..58,B34..45,NM.
+    /// Defaults to the pattern rule, or B3/S23.
+    #[arg(short, long)]
+    pub rule: Option<Rule>,
+
+    /// What happens at the grid edges, clamp, wrap or unbounded. Unbounded
+    /// universes have no edges, the grid size being the area filled at start.
+    #[arg(short, long, default_value = "clamp")]
+    pub border: BorderPolicy,
+
+    /// Compute generations with HashLife, jumping many at once on repetitive
+    /// patterns. Needs a two state rule with the Moore neighbourhood and makes
+    /// the universe unbounded.
+    #[arg(long)]
+    pub hyperspeed: bool,
+
+    /// Pattern to start from instead of a random grid (.rle, .cells, .lif or .life).
+    #[arg(short, long)]
+    pub pattern: Option<PathBuf>,
+
+    /// Where to place the pattern top left corner, as x,y. Centred by default.
+    #[arg(long, value_parser = parse_pair::<','>)]
+    pub offset: Option<(usize, usize)>,
+
+    /// Seed of the random initial state. A new one is picked when not given.
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+
+    /// Ratio of alive cells in the random initial state, from 0.0 to 1.0.
+    #[arg(short, long, default_value_t = 0.5, value_parser = parse_density)]
+    pub density: f64,
+
+    /// Grid size as WIDTHxHEIGHT. Sized to the terminal by default.
+    #[arg(long, value_parser = parse_size)]
+    pub size: Option<(usize, usize)>,
+
+    /// Point of the grid kept in place when it follows a terminal resize,
+    /// top-left or centre.
+    #[arg(long, default_value = "centre")]
+    pub resize_anchor: Anchor,
+
+    /// Never shrink the grid when the terminal gets smaller, only crop the view.
+    #[arg(long)]
+    pub never_shrink: bool,
+
+    /// Threads computing each generation, in bands of rows. Defaults to the
+    /// amount of cores.
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Milliseconds between generations.
+    #[arg(short, long, default_value_t = 75)]
+    pub update_ms: u64,
+
+    /// Milliseconds between input polls.
+    #[arg(long, default_value_t = 250)]
+    pub input_ms: u64,
+
+    /// Start running instead of paused.
+    #[arg(long)]
+    pub play: bool,
+
+    /// Memory the undo history may take, in megabytes. Zero disables it.
+    #[arg(long, default_value_t = 64)]
+    pub history_mb: usize,
+
+    /// Run the given amount of generations without the terminal interface,
+    /// then print the final population, bounding box and elapsed time.
+    #[arg(long, value_name = "GENERATIONS")]
+    pub headless: Option<usize>,
+
+    /// Pattern file the final state of a headless run is written to.
+    #[arg(short, long, requires = "headless")]
+    pub output: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Threads to compute generations with, from `--threads` or the cores.
+    pub fn thread_count(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |threads| threads.get())
+        })
+    }
+}
+
+fn parse_pair<const SEPARATOR: char>(value: &str) -> Result<(usize, usize), String> {
+    let (a, b) = value
+        .split_once(SEPARATOR)
+        .ok_or_else(|| format!("expected two numbers separated by '{}'", SEPARATOR))?;
+    let a = a.trim().parse::<usize>().map_err(|e| e.to_string())?;
+    let b = b.trim().parse::<usize>().map_err(|e| e.to_string())?;
+    Ok((a, b))
+}
+
+fn parse_size(value: &str) -> Result<(usize, usize), String> {
+    match parse_pair::<'x'>(value)? {
+        (0, _) | (_, 0) => Err("grid size must not be zero".to_string()),
+        size => Ok(size),
+    }
+}
+
+fn parse_density(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(density) if (0.0..=1.0).contains(&density) => Ok(density),
+        Ok(_) => Err("density must be between 0.0 and 1.0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
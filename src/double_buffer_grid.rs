@@ -1,18 +1,28 @@
-use crate::{cell_state::CellState, border_policy::BorderPolicy};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    active_tiles::ActiveTiles, anchor::Anchor, border_policy::BorderPolicy, cell_state::CellState,
+    history::Change, pattern::Pattern, rule::Rule,
+};
+
+/// Width and height of the tiles whose activity is tracked.
+const TILE_SIZE: usize = 16;
 
 pub struct DoubleBufferGrid {
     grids: [Vec<CellState>; 2],
     cycle: usize,
     width: usize,
     height: usize,
+    tiles: ActiveTiles,
 }
 
 impl DoubleBufferGrid {
     pub fn new(width: usize, height: usize) -> Self {
         let size = width * height;
         let grids = [
-            std::iter::repeat(CellState::Dead).take(size).collect(),
-            std::iter::repeat(CellState::Dead).take(size).collect(),
+            vec![CellState::Dead; size],
+            vec![CellState::Dead; size],
         ];
 
         Self {
@@ -20,13 +30,54 @@ impl DoubleBufferGrid {
             cycle: 0,
             width,
             height,
+            tiles: ActiveTiles::new(width, height, TILE_SIZE, TILE_SIZE),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Changes the grid size, keeping the cells in place around the anchor.
+    /// Cells falling outside the new size are dropped.
+    pub fn resize(&mut self, width: usize, height: usize, anchor: Anchor) {
+        let offset_x = anchor.offset(self.width, width);
+        let offset_y = anchor.offset(self.height, height);
+
+        for grid in self.grids.iter_mut() {
+            let mut resized = vec![CellState::Dead; width * height];
+            for (index, cell) in grid.iter().enumerate() {
+                let x = (index % self.width) as isize + offset_x;
+                let y = (index / self.width) as isize + offset_y;
+                if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                    continue;
+                }
+                resized[y as usize * width + x as usize] = cell.clone();
+            }
+            *grid = resized;
         }
+
+        self.width = width;
+        self.height = height;
+        self.tiles = ActiveTiles::new(width, height, TILE_SIZE, TILE_SIZE);
     }
 
-    pub fn randomize(&mut self) {
+    /// Tiles computed on the next step.
+    pub fn tiles(&self) -> &ActiveTiles {
+        &self.tiles
+    }
+
+    /// Fills the grid with random cells, `density` being the probability of a cell
+    /// to be alive. The same seed always gives the same grid, on every platform.
+    pub fn randomize(&mut self, seed: u64, density: f64) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
         let size = self.width * self.height;
         for i in 0..size {
-            let state = if rand::random() {
+            let state = if rng.gen_bool(density) {
                 CellState::Alive(0)
             } else {
                 CellState::Dead
@@ -34,6 +85,222 @@ impl DoubleBufferGrid {
             self.grids[0][i] = state.clone();
             self.grids[1][i] = state.clone();
         }
+        self.tiles.mark_all();
+    }
+
+    pub fn clear(&mut self) {
+        for grid in self.grids.iter_mut() {
+            grid.fill(CellState::Dead);
+        }
+        self.tiles.mark_all();
+    }
+
+    /// Sets a cell on both buffers, so it is both rendered and read by the next update.
+    pub fn set_cell(&mut self, x: usize, y: usize, state: CellState) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let index = y * self.width + x;
+        self.grids[0][index] = state.clone();
+        self.grids[1][index] = state;
+        self.tiles.mark_cell(x, y);
+    }
+
+    /// Places the alive cells of a pattern with its top left corner at the given point.
+    /// Cells falling outside the grid are dropped.
+    pub fn place_pattern(&mut self, pattern: &Pattern, x: usize, y: usize) {
+        for (cell_x, cell_y) in pattern.cells.iter() {
+            self.set_cell(x + cell_x, y + cell_y, CellState::Alive(0));
+        }
+    }
+
+    /// Changes the age of every alive cell on both buffers.
+    pub fn age_cells(&mut self, delta: isize) {
+        for grid in self.grids.iter_mut() {
+            for cell in grid.iter_mut() {
+                if let CellState::Alive(age) = cell {
+                    *age = age.saturating_add_signed(delta);
+                }
+            }
+        }
+    }
+
+    /// Clears the grid and places a pattern, centred unless an offset is given.
+    pub fn load_pattern(&mut self, pattern: &Pattern, offset: Option<(usize, usize)>) {
+        let (x, y) = offset.unwrap_or((
+            self.width.saturating_sub(pattern.width) / 2,
+            self.height.saturating_sub(pattern.height) / 2,
+        ));
+
+        self.clear();
+        self.place_pattern(pattern, x, y);
+    }
+
+    /// Amount of alive cells in the render grid.
+    pub fn population(&self) -> usize {
+        self.get_render_grid()
+            .iter()
+            .filter(|cell| matches!(cell, CellState::Alive(_)))
+            .count()
+    }
+
+    /// Smallest rectangle containing every alive cell of the render grid,
+    /// as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let alive = self
+            .get_render_grid()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| matches!(cell, CellState::Alive(_)))
+            .map(|(index, _)| (index % self.width, index / self.width))
+            .collect::<Vec<(usize, usize)>>();
+
+        let min_x = alive.iter().map(|(x, _)| *x).min()?;
+        let min_y = alive.iter().map(|(_, y)| *y).min()?;
+        let max_x = alive.iter().map(|(x, _)| *x).max()?;
+        let max_y = alive.iter().map(|(_, y)| *y).max()?;
+        Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    /// Cells of the render grid that are not dead, with their state.
+    pub fn cells(&self) -> Vec<((i64, i64), CellState)> {
+        self.get_render_grid()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell != CellState::Dead)
+            .map(|(index, cell)| {
+                let position = ((index % self.width) as i64, (index / self.width) as i64);
+                (position, cell.clone())
+            })
+            .collect()
+    }
+
+    /// Positions of the alive cells of the render grid.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        self.get_render_grid()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| matches!(cell, CellState::Alive(_)))
+            .map(|(index, _)| ((index % self.width) as i64, (index / self.width) as i64))
+            .collect()
+    }
+
+    /// Positions of the alive cells of the render grid inside a rectangle.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        let min_x = x.clamp(0, self.width as i64) as usize;
+        let min_y = y.clamp(0, self.height as i64) as usize;
+        let max_x = (x + width as i64).clamp(0, self.width as i64) as usize;
+        let max_y = (y + height as i64).clamp(0, self.height as i64) as usize;
+
+        let grid = self.get_render_grid();
+        let mut alive = Vec::new();
+        for cell_y in min_y..max_y {
+            for cell_x in min_x..max_x {
+                if let CellState::Alive(_) = grid[cell_y * self.width + cell_x] {
+                    alive.push((cell_x as i64, cell_y as i64));
+                }
+            }
+        }
+        alive
+    }
+
+    /// Pattern made of the alive cells of the render grid, cropped to their bounding box.
+    pub fn to_pattern(&self) -> Pattern {
+        Pattern::from_cells(&self.alive_cells())
+    }
+
+    /// Computes the next generation from the read grid into the write grid.
+    pub fn step(&mut self, rule: &Rule, policy: BorderPolicy) {
+        self.step_in_bands(rule, policy, 1);
+    }
+
+    /// Computes the next generation with the rows split in bands, each computed
+    /// on its own thread. The cells are the same whatever the amount of threads.
+    /// Only the active tiles are computed, the others keep their cells.
+    pub fn step_in_bands(&mut self, rule: &Rule, policy: BorderPolicy, threads: usize) {
+        self.add_cycle();
+        self.tiles.start_step(rule, &policy);
+
+        let offsets = rule.neighbour_offsets();
+        // The write grid is taken out so the threads can share the read grid.
+        let write_index = (self.cycle + 1) % 2;
+        let mut write = std::mem::take(&mut self.grids[write_index]);
+        let band_rows = self.height.div_ceil(threads.max(1));
+
+        if threads <= 1 || band_rows == 0 || self.width == 0 {
+            self.step_rows(0, &mut write, rule, &policy, &offsets);
+        } else {
+            let (this, policy, offsets) = (&*self, &policy, &offsets);
+            std::thread::scope(|scope| {
+                for (band, cells) in write.chunks_mut(band_rows * self.width).enumerate() {
+                    scope.spawn(move || {
+                        this.step_rows(band * band_rows, cells, rule, policy, offsets)
+                    });
+                }
+            });
+        }
+
+        self.grids[write_index] = write;
+        self.tiles.finish_step(&self.changed_tiles());
+    }
+
+    /// Tiles where a cell went from a state to another in the last step, ages aside.
+    fn changed_tiles(&self) -> Vec<bool> {
+        let columns = self.tiles.columns();
+        let mut changed = vec![false; columns * self.height.div_ceil(TILE_SIZE)];
+        let cells = self.get_read_grid().iter().zip(self.get_render_grid().iter());
+        for (index, (before, after)) in cells.enumerate() {
+            let (x, y) = (index % self.width, index / self.width);
+            let same = match (before, after) {
+                (CellState::Alive(_), CellState::Alive(_)) => true,
+                (before, after) => before == after,
+            };
+            if !same {
+                changed[(y / TILE_SIZE) * columns + x / TILE_SIZE] = true;
+            }
+        }
+        changed
+    }
+
+    /// Computes the rows from `first_row` into `cells`, which holds whole rows.
+    fn step_rows(
+        &self,
+        first_row: usize,
+        cells: &mut [CellState],
+        rule: &Rule,
+        policy: &BorderPolicy,
+        offsets: &[(i32, i32)],
+    ) {
+        let read = self.get_read_grid();
+        for (band_index, cell) in cells.iter_mut().enumerate() {
+            let index = first_row * self.width + band_index;
+            let (x, y) = (index % self.width, index / self.width);
+            // Cells of a stable tile are alive or dead, alive ones getting older.
+            if !self.tiles.is_active(x, y) {
+                *cell = match &read[index] {
+                    CellState::Alive(age) => CellState::Alive(age + 1),
+                    state => state.clone(),
+                };
+                continue;
+            }
+            let alive_neighbours =
+                self.get_alive_neighbours_at_point(x, y, policy.clone(), offsets);
+            *cell = rule.next_state(&read[index], alive_neighbours);
+        }
+    }
+
+    /// Cells changed by the last step, see [`Change::generation`].
+    pub fn changes(&self) -> Vec<Change> {
+        self.get_read_grid()
+            .iter()
+            .zip(self.get_render_grid().iter())
+            .enumerate()
+            .filter_map(|(index, (before, after))| {
+                let cell = ((index % self.width) as i64, (index / self.width) as i64);
+                Change::generation(cell, before, after)
+            })
+            .collect()
     }
 
     pub fn add_cycle(&mut self) {
@@ -55,55 +322,105 @@ impl DoubleBufferGrid {
         &self.grids[write_grid_index]
     }
 
-    pub fn get_alive_neighbours_at_point(&self, x: usize, y: usize, policy: BorderPolicy) -> usize {
-        let mut indices = Vec::with_capacity(8);
-        for yy in (-1 as i32)..2 {
-            for xx in (-1 as i32)..2 {
-                if xx == 0 && yy == 0 {
-                    continue;
-                }
+    /// Counts the alive cells found at the given offsets from a point.
+    pub fn get_alive_neighbours_at_point(
+        &self,
+        x: usize,
+        y: usize,
+        policy: BorderPolicy,
+        offsets: &[(i32, i32)],
+    ) -> usize {
+        let read = self.get_read_grid();
+        let width = self.width as i32;
+        let height = self.height as i32;
 
-                let mut new_x = x as i32 + xx;
-                let mut new_y = y as i32 + yy;
+        offsets
+            .iter()
+            .filter_map(|(xx, yy)| {
+                let new_x = x as i32 + xx;
+                let new_y = y as i32 + yy;
 
-                if policy == BorderPolicy::Clamp {
-                    if new_x < 0
-                        || new_x >= self.width as i32
-                        || new_y < 0
-                        || new_y >= self.height as i32
-                    {
-                        continue;
-                    }
-                } else if policy == BorderPolicy::Wrap {
-                    // Would be great to use modulus but it's not working with negative numbers 🙃
-                    // new_x = new_x % (self.width as i32);
-                    // new_y = new_y % (self.height as i32);
-                    if new_x < 0 {
-                        new_x = (self.width - 1) as i32;
-                    }
-                    if new_x >= self.width as i32 {
-                        new_x = 0;
-                    }
-                    if new_y < 0 {
-                        new_y = (self.height - 1) as i32;
-                    }
-                    if new_y >= self.height as i32 {
-                        new_y = 0;
+                match policy {
+                    // An unbounded universe is stored in a sparse grid, the grid
+                    // edges are then treated as clamped.
+                    BorderPolicy::Clamp | BorderPolicy::Unbounded => {
+                        if new_x < 0 || new_x >= width || new_y < 0 || new_y >= height {
+                            return None;
+                        }
+                        Some((new_x, new_y))
                     }
+                    BorderPolicy::Wrap => Some((new_x.rem_euclid(width), new_y.rem_euclid(height))),
                 }
-
-                let index = (new_y as usize) * self.width + (new_x as usize);
-                indices.push(index);
-            }
-        }
-
-        indices
-            .iter()
-            .map(|i| &self.get_read_grid()[*i])
+            })
+            .map(|(new_x, new_y)| &read[(new_y as usize) * self.width + (new_x as usize)])
             .filter(|cs| match *cs {
                 CellState::Alive(_) => true,
-                CellState::Dead => false,
+                CellState::Dying(_) | CellState::Dead => false,
             })
             .count()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(grid: &DoubleBufferGrid, x: usize, y: usize, policy: BorderPolicy) -> usize {
+        let offsets = Rule::conway().neighbour_offsets();
+        grid.get_alive_neighbours_at_point(x, y, policy, &offsets)
+    }
+
+    fn full_grid(width: usize, height: usize) -> DoubleBufferGrid {
+        let mut grid = DoubleBufferGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set_cell(x, y, CellState::Alive(0));
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn clamped_corners_and_edges_have_fewer_neighbours() {
+        let grid = full_grid(4, 4);
+        for (x, y) in [(0, 0), (3, 0), (0, 3), (3, 3)] {
+            let neighbours = count(&grid, x, y, BorderPolicy::Clamp);
+            assert_eq!(neighbours, 3, "corner ({}, {})", x, y);
+        }
+        assert_eq!(count(&grid, 0, 1, BorderPolicy::Clamp), 5);
+        assert_eq!(count(&grid, 1, 1, BorderPolicy::Clamp), 8);
+    }
+
+    #[test]
+    fn wrapped_corners_have_every_neighbour() {
+        let grid = full_grid(4, 4);
+        for (x, y) in [(0, 0), (3, 0), (0, 3), (3, 3)] {
+            let neighbours = count(&grid, x, y, BorderPolicy::Wrap);
+            assert_eq!(neighbours, 8, "corner ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn opposite_corners_are_neighbours_only_when_wrapped() {
+        let mut grid = DoubleBufferGrid::new(4, 4);
+        grid.set_cell(3, 3, CellState::Alive(0));
+        assert_eq!(count(&grid, 0, 0, BorderPolicy::Wrap), 1);
+        assert_eq!(count(&grid, 0, 0, BorderPolicy::Clamp), 0);
+    }
+
+    #[test]
+    fn dying_cells_are_not_counted() {
+        let mut grid = DoubleBufferGrid::new(3, 3);
+        grid.set_cell(0, 0, CellState::Dying(1));
+        grid.set_cell(2, 2, CellState::Alive(4));
+        assert_eq!(count(&grid, 1, 1, BorderPolicy::Clamp), 1);
+    }
+
+    #[test]
+    fn wrapping_a_grid_smaller_than_the_neighbourhood_counts_cells_again() {
+        // On a 2x2 torus each neighbour is reached through two offsets.
+        let mut grid = DoubleBufferGrid::new(2, 2);
+        grid.set_cell(1, 0, CellState::Alive(0));
+        assert_eq!(count(&grid, 0, 0, BorderPolicy::Wrap), 2);
+    }
+}
@@ -0,0 +1,39 @@
+/// Cells crossed by the straight line between two cells, both included,
+/// following Bresenham's algorithm.
+pub fn line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (mut x, mut y) = from;
+    let (to_x, to_y) = to;
+    let dx = (to_x - x).abs();
+    let dy = -(to_y - y).abs();
+    let step_x = if x < to_x { 1 } else { -1 };
+    let step_y = if y < to_y { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    let mut cells = Vec::new();
+    loop {
+        cells.push((x, y));
+        if x == to_x && y == to_y {
+            break;
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+    cells
+}
+
+/// Cells of the filled rectangle with the two cells as opposite corners.
+pub fn rectangle(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (min_x, max_x) = (from.0.min(to.0), from.0.max(to.0));
+    let (min_y, max_y) = (from.1.min(to.1), from.1.max(to.1));
+    (min_y..=max_y)
+        .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+        .collect()
+}
@@ -0,0 +1,21 @@
+/// How the keyboard edits the grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditMode {
+    /// Keys control the simulation, the cursor is hidden.
+    Off,
+    /// The cursor moves freely and toggles the cell under it.
+    Cursor,
+    /// Draws a line from the anchor to the cursor once confirmed.
+    Line { anchor: (i64, i64) },
+    /// Fills the rectangle between the anchor and the cursor once confirmed.
+    Rectangle { anchor: (i64, i64) },
+}
+
+impl EditMode {
+    pub fn switch(&mut self) {
+        *self = match self {
+            Self::Off => Self::Cursor,
+            _ => Self::Off,
+        };
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    active_tiles::ActiveTiles, anchor::Anchor, border_policy::BorderPolicy, cell_state::CellState,
+    double_buffer_grid::DoubleBufferGrid, hashlife::HashLife, history::Change,
+    packed_grid::PackedGrid, rule::Rule, sparse_grid::SparseGrid,
+};
+
+/// Fewest rows given to each thread of a parallel step, smaller grids using
+/// fewer threads as starting them would cost more than it saves.
+const MIN_BAND_ROWS: usize = 16;
+
+/// How the cells are stored and the generations computed.
+///
+/// Cells are addressed with signed coordinates so unbounded universes can
+/// grow in every direction. Bounded engines only hold the cells from the
+/// origin to their size.
+pub enum Engine {
+    /// A dense double buffered grid, clamped or wrapped at its edges.
+    Grid(DoubleBufferGrid),
+    /// One bit per cell, clamped or wrapped at its edges, limited to two state
+    /// Moore rules.
+    Packed(PackedGrid),
+    /// The cells that are not dead, in an unbounded universe.
+    Sparse(SparseGrid),
+    /// A memoised quadtree in an unbounded universe, jumping many generations
+    /// at once but limited to two state Moore rules.
+    HashLife(HashLife),
+}
+
+impl Engine {
+    /// Engine fitting a border policy, bounded ones getting the given size.
+    pub fn for_policy(policy: &BorderPolicy, width: usize, height: usize) -> Self {
+        match policy {
+            BorderPolicy::Clamp | BorderPolicy::Wrap => {
+                Self::Grid(DoubleBufferGrid::new(width, height))
+            }
+            BorderPolicy::Unbounded => Self::Sparse(SparseGrid::new()),
+        }
+    }
+
+    /// Whether the engine only holds the cells from the origin to its size.
+    pub fn is_bounded(&self) -> bool {
+        match self {
+            Self::Grid(_) | Self::Packed(_) => true,
+            Self::Sparse(_) | Self::HashLife(_) => false,
+        }
+    }
+
+    /// Tiles the next step computes, for the bounded engines skipping stable tiles.
+    pub fn active_tiles(&self) -> Option<&ActiveTiles> {
+        match self {
+            Self::Grid(grid) => Some(grid.tiles()),
+            Self::Packed(grid) => Some(grid.tiles()),
+            Self::Sparse(_) | Self::HashLife(_) => None,
+        }
+    }
+
+    /// Changes the size of a bounded engine, keeping the cells in place around
+    /// the anchor. Unbounded engines are left as they are.
+    pub fn resize(&mut self, width: usize, height: usize, anchor: Anchor) {
+        match self {
+            Self::Grid(grid) => grid.resize(width, height, anchor),
+            Self::Packed(grid) => grid.resize(width, height, anchor),
+            Self::Sparse(_) | Self::HashLife(_) => {}
+        }
+    }
+
+    /// Computes the next generation, bounded engines splitting their rows
+    /// between up to the given amount of threads.
+    pub fn step(&mut self, rule: &Rule, policy: &BorderPolicy, threads: usize) {
+        match self {
+            Self::Grid(grid) => {
+                let threads = threads.min(grid.height() / MIN_BAND_ROWS);
+                grid.step_in_bands(rule, policy.clone(), threads)
+            }
+            Self::Packed(grid) => {
+                let threads = threads.min(grid.height() / MIN_BAND_ROWS);
+                grid.step_in_bands(rule, policy.clone(), threads)
+            }
+            Self::Sparse(grid) => grid.step(rule),
+            Self::HashLife(hashlife) => hashlife.step_pow2(rule, 0),
+        }
+    }
+
+    /// Cells changed by the last step, see [`Change::generation`]. HashLife
+    /// does not keep the previous generation and reports none.
+    pub fn changes(&self) -> Vec<Change> {
+        match self {
+            Self::Grid(grid) => grid.changes(),
+            Self::Packed(grid) => grid.changes(),
+            Self::Sparse(grid) => grid.changes(),
+            Self::HashLife(_) => Vec::new(),
+        }
+    }
+
+    /// State of a cell, `None` outside of a bounded engine.
+    pub fn get_cell(&self, x: i64, y: i64) -> Option<CellState> {
+        match self {
+            Self::Grid(grid) => {
+                let (x, y) = grid_position(grid.width(), grid.height(), x, y)?;
+                grid.get_render_grid().get(y * grid.width() + x).cloned()
+            }
+            Self::Packed(grid) => {
+                let (x, y) = grid_position(grid.width(), grid.height(), x, y)?;
+                Some(grid.get_cell(x, y))
+            }
+            Self::Sparse(grid) => Some(grid.get_cell(x, y).clone()),
+            Self::HashLife(hashlife) => Some(hashlife.get_cell(x, y).clone()),
+        }
+    }
+
+    /// Sets a cell, ignored outside of a bounded engine.
+    pub fn set_cell(&mut self, x: i64, y: i64, state: CellState) {
+        match self {
+            Self::Grid(grid) => {
+                if let Some((x, y)) = grid_position(grid.width(), grid.height(), x, y) {
+                    grid.set_cell(x, y, state);
+                }
+            }
+            Self::Packed(grid) => {
+                if let Some((x, y)) = grid_position(grid.width(), grid.height(), x, y) {
+                    grid.set_cell(x, y, state);
+                }
+            }
+            Self::Sparse(grid) => grid.set_cell(x, y, state),
+            Self::HashLife(hashlife) => hashlife.set_cell(x, y, state),
+        }
+    }
+
+    /// Changes the age of every alive cell, HashLife cells having none.
+    pub fn age_cells(&mut self, delta: isize) {
+        match self {
+            Self::Grid(grid) => grid.age_cells(delta),
+            Self::Packed(grid) => grid.age_cells(delta),
+            Self::Sparse(grid) => grid.age_cells(delta),
+            Self::HashLife(_) => {}
+        }
+    }
+
+    /// Fills the rectangle from the origin to the given size with random cells,
+    /// the same seed giving the same cells whatever the engine.
+    pub fn randomize(&mut self, seed: u64, density: f64, width: usize, height: usize) {
+        match self {
+            Self::Grid(grid) => grid.randomize(seed, density),
+            Self::Packed(grid) => grid.randomize(seed, density),
+            Self::Sparse(grid) => grid.randomize(seed, density, width, height),
+            Self::HashLife(hashlife) => hashlife.randomize(seed, density, width, height),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Self::Grid(grid) => grid.clear(),
+            Self::Packed(grid) => grid.clear(),
+            Self::Sparse(grid) => grid.clear(),
+            Self::HashLife(hashlife) => hashlife.clear(),
+        }
+    }
+
+    /// Amount of alive cells.
+    pub fn population(&self) -> usize {
+        match self {
+            Self::Grid(grid) => grid.population(),
+            Self::Packed(grid) => grid.population(),
+            Self::Sparse(grid) => grid.population(),
+            Self::HashLife(hashlife) => hashlife.population(),
+        }
+    }
+
+    /// Smallest rectangle containing every alive cell, as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(i64, i64, usize, usize)> {
+        match self {
+            Self::Grid(grid) => grid
+                .bounding_box()
+                .map(|(x, y, width, height)| (x as i64, y as i64, width, height)),
+            Self::Packed(grid) => grid
+                .bounding_box()
+                .map(|(x, y, width, height)| (x as i64, y as i64, width, height)),
+            Self::Sparse(grid) => grid.bounding_box(),
+            Self::HashLife(hashlife) => hashlife.bounding_box(),
+        }
+    }
+
+    /// Cells that are not dead, with their state.
+    pub fn cells(&self) -> Vec<((i64, i64), CellState)> {
+        match self {
+            Self::Grid(grid) => grid.cells(),
+            Self::Packed(grid) => grid.cells(),
+            Self::Sparse(grid) => grid.cells(),
+            Self::HashLife(hashlife) => hashlife
+                .alive_cells()
+                .into_iter()
+                .map(|position| (position, CellState::Alive(0)))
+                .collect(),
+        }
+    }
+
+    /// Positions of the alive cells.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        match self {
+            Self::Grid(grid) => grid.alive_cells(),
+            Self::Packed(grid) => grid.alive_cells(),
+            Self::Sparse(grid) => grid.alive_cells(),
+            Self::HashLife(hashlife) => hashlife.alive_cells(),
+        }
+    }
+
+    /// Positions of the alive cells inside a rectangle.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        match self {
+            Self::Grid(grid) => grid.alive_cells_in(x, y, width, height),
+            Self::Packed(grid) => grid.alive_cells_in(x, y, width, height),
+            Self::Sparse(grid) => grid.alive_cells_in(x, y, width, height),
+            Self::HashLife(hashlife) => hashlife.alive_cells_in(x, y, width, height),
+        }
+    }
+}
+
+/// Position inside a grid of the given size, `None` when out of it.
+fn grid_position(width: usize, height: usize, x: i64, y: i64) -> Option<(usize, usize)> {
+    if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+        return None;
+    }
+    Some((x as usize, y as usize))
+}
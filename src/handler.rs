@@ -1,38 +1,332 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 
-use crate::app::App;
+use crate::{app::App, edit_mode::EditMode};
+
+/// Cells the cursor or the view moves when shift is held.
+const FAST_STEP: i64 = 8;
+
+/// History entries each scrub key press goes through.
+const SCRUB_STEP: usize = 10;
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> anyhow::Result<()> {
+    if app.edit_mode != EditMode::Off && handle_edit_key_events(key_event, app) {
+        return Ok(());
+    }
+
     match key_event.code {
-        // Exit application on `ESC` or `q`
-        KeyCode::Esc | KeyCode::Char('q') => {
+        // Exit application on `ESC` or `q`, on the press as terminals without the
+        // keyboard enhancement flags report no releases.
+        KeyCode::Esc | KeyCode::Char('q') if key_event.kind == KeyEventKind::Press => {
             app.quit();
         }
         // Exit application on `Ctrl-C`
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.quit();
-            }
+        KeyCode::Char('c') | KeyCode::Char('C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.quit();
         }
-        KeyCode::Char('r') => {
-            if key_event.kind == KeyEventKind::Release {
-                app.randomize_cells();
-                app.cycle_count = 0;
-            }
+        KeyCode::Char('r') if key_event.kind == KeyEventKind::Release => {
+            app.randomize_cells();
+            app.log(format!("Randomized with seed {}", app.seed));
         }
-        KeyCode::Char('p') => {
-            if key_event.kind == KeyEventKind::Release {
-                app.player_state.switch();
-            }
+        KeyCode::Char('R') if key_event.kind == KeyEventKind::Release => {
+            app.replay_seed();
+            app.log(format!("Randomized again with seed {}", app.seed));
         }
-        KeyCode::Char('b') => {
-            if key_event.kind == KeyEventKind::Release {
-                app.border_policy.switch();
-            }
+        KeyCode::Char('d') if key_event.kind == KeyEventKind::Release => {
+            app.change_density(-0.05);
+        }
+        KeyCode::Char('D') if key_event.kind == KeyEventKind::Release => {
+            app.change_density(0.05);
+        }
+        KeyCode::Char('p') if key_event.kind == KeyEventKind::Release => {
+            app.player_state.switch();
+        }
+        KeyCode::Char('b') if key_event.kind == KeyEventKind::Release => {
+            app.switch_border_policy();
+        }
+        KeyCode::Char('H') if key_event.kind == KeyEventKind::Release => {
+            app.switch_hyperspeed();
+        }
+        KeyCode::Char('n') if key_event.kind == KeyEventKind::Release => {
+            app.next_rule();
+        }
+        KeyCode::Char('N') if key_event.kind == KeyEventKind::Release => {
+            app.previous_rule();
+        }
+        KeyCode::Char('s') if key_event.kind == KeyEventKind::Release => {
+            app.save_snapshot();
+        }
+        KeyCode::Char('e') if key_event.kind == KeyEventKind::Release => {
+            app.switch_edit_mode();
+        }
+        // Pan the view by grid characters, reacting to presses so holding the key keeps going.
+        KeyCode::Left | KeyCode::Char('h') if key_event.kind != KeyEventKind::Release => {
+            let (dx, _) = app.layout.cells_in_chars(move_step(&key_event), 0);
+            app.pan(-dx, 0);
+        }
+        KeyCode::Right | KeyCode::Char('l') if key_event.kind != KeyEventKind::Release => {
+            let (dx, _) = app.layout.cells_in_chars(move_step(&key_event), 0);
+            app.pan(dx, 0);
+        }
+        KeyCode::Up | KeyCode::Char('k') if key_event.kind != KeyEventKind::Release => {
+            let (_, dy) = app.layout.cells_in_chars(0, move_step(&key_event));
+            app.pan(0, -dy);
+        }
+        KeyCode::Down | KeyCode::Char('j') if key_event.kind != KeyEventKind::Release => {
+            let (_, dy) = app.layout.cells_in_chars(0, move_step(&key_event));
+            app.pan(0, dy);
+        }
+        KeyCode::Char('c') if key_event.kind == KeyEventKind::Release => {
+            app.centre_on_population();
+        }
+        KeyCode::Char('f') if key_event.kind == KeyEventKind::Release => {
+            app.switch_follow_population();
+        }
+        KeyCode::Char('t') if key_event.kind == KeyEventKind::Release => {
+            app.show_active_tiles = !app.show_active_tiles;
+        }
+        KeyCode::Char('z') if key_event.kind == KeyEventKind::Release => {
+            app.zoom_in();
+        }
+        KeyCode::Char('Z') if key_event.kind == KeyEventKind::Release => {
+            app.zoom_out();
+        }
+        // Step one generation on `.`, or as many as typed before it, e.g. `25.`
+        KeyCode::Char(c) if c.is_ascii_digit() && key_event.kind == KeyEventKind::Release => {
+            app.type_step_digit(c.to_digit(10).unwrap_or(0) as usize);
+        }
+        KeyCode::Backspace if key_event.kind == KeyEventKind::Release => {
+            app.erase_step_digit();
+        }
+        KeyCode::Char('.') if key_event.kind == KeyEventKind::Release => {
+            app.step();
+        }
+        KeyCode::Char('u') if key_event.kind == KeyEventKind::Release => {
+            app.undo(1);
+        }
+        KeyCode::Char('U') if key_event.kind == KeyEventKind::Release => {
+            app.redo(1);
+        }
+        // Scrub the timeline, reacting to presses so holding the key keeps going.
+        KeyCode::Char('[') if key_event.kind != KeyEventKind::Release => {
+            app.undo(SCRUB_STEP);
+        }
+        KeyCode::Char(']') if key_event.kind != KeyEventKind::Release => {
+            app.redo(SCRUB_STEP);
+        }
+        KeyCode::Char('+' | '=') if key_event.kind == KeyEventKind::Release => {
+            app.speed.faster();
+        }
+        KeyCode::Char('-') if key_event.kind == KeyEventKind::Release => {
+            app.speed.slower();
+        }
+        // Doubles or halves the generations per update, the jump size in hyperspeed.
+        KeyCode::Char('>') if key_event.kind == KeyEventKind::Release => {
+            app.more_per_update();
+        }
+        KeyCode::Char('<') if key_event.kind == KeyEventKind::Release => {
+            app.fewer_per_update();
         }
         // Other handlers you could add here.
         _ => {}
     }
     Ok(())
 }
+
+/// Cells a move key goes through, more when shift is held.
+fn move_step(key_event: &KeyEvent) -> i64 {
+    if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+        FAST_STEP
+    } else {
+        1
+    }
+}
+
+/// Handles the key events of the edit mode, returning whether the key was used.
+///
+/// Cursor moves react to presses rather than releases so holding a key repeats them.
+fn handle_edit_key_events(key_event: KeyEvent, app: &mut App) -> bool {
+    let step = move_step(&key_event);
+    let pressed = key_event.kind != KeyEventKind::Release;
+    let released = key_event.kind == KeyEventKind::Release;
+
+    match key_event.code {
+        KeyCode::Left | KeyCode::Char('h') if pressed => app.move_cursor(-step, 0),
+        KeyCode::Right | KeyCode::Char('l') if pressed => app.move_cursor(step, 0),
+        KeyCode::Up | KeyCode::Char('k') if pressed => app.move_cursor(0, -step),
+        KeyCode::Down | KeyCode::Char('j') if pressed => app.move_cursor(0, step),
+        KeyCode::Char(' ') if released => match app.edit_mode {
+            EditMode::Cursor => app.toggle_cell_at_cursor(),
+            _ => app.confirm_edit(true),
+        },
+        KeyCode::Enter if released => app.confirm_edit(true),
+        KeyCode::Backspace | KeyCode::Delete if released => app.confirm_edit(false),
+        KeyCode::Char('L') if released => {
+            app.edit_mode = EditMode::Line { anchor: app.cursor };
+        }
+        KeyCode::Char('F') if released => {
+            app.edit_mode = EditMode::Rectangle { anchor: app.cursor };
+        }
+        // Leaving on the press, the release then reaching the main handler which ignores it.
+        KeyCode::Esc if key_event.kind == KeyEventKind::Press => {
+            app.edit_mode = match app.edit_mode {
+                EditMode::Line { .. } | EditMode::Rectangle { .. } => EditMode::Cursor,
+                _ => EditMode::Off,
+            };
+        }
+        // The other events of the keys above, kept from the main handler.
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Enter => {}
+        KeyCode::Esc => {}
+        KeyCode::Char('h' | 'j' | 'k' | 'l' | ' ' | 'L' | 'F') => {}
+        KeyCode::Backspace | KeyCode::Delete => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles the mouse events, painting cells alive with the left button and
+/// dead with the right button, panning the view with the middle button and
+/// zooming with the wheel.
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> anyhow::Result<()> {
+    let (column, row) = (mouse_event.column, mouse_event.row);
+    let cell = app.cell_at(column, row);
+    match (mouse_event.kind, cell) {
+        (MouseEventKind::Down(MouseButton::Middle), Some(_)) => app.start_pan(column, row),
+        (MouseEventKind::Drag(MouseButton::Middle), _) => app.pan_to(column, row),
+        (MouseEventKind::Up(MouseButton::Middle), _) => app.end_pan(),
+        (MouseEventKind::ScrollUp, _) => app.zoom_in(),
+        (MouseEventKind::ScrollDown, _) => app.zoom_out(),
+        (MouseEventKind::Down(button), Some(cell)) => {
+            app.end_stroke();
+            paint_with_button(button, cell, app);
+        }
+        (MouseEventKind::Drag(button), Some(cell)) => {
+            paint_with_button(button, cell, app);
+        }
+        // Dragging out of the grid breaks the stroke.
+        (MouseEventKind::Drag(_), None) | (MouseEventKind::Up(_), _) => {
+            app.end_stroke();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn paint_with_button(button: MouseButton, cell: (i64, i64), app: &mut App) {
+    match button {
+        MouseButton::Left => app.paint_to(cell, true),
+        MouseButton::Right => app.paint_to(cell, false),
+        MouseButton::Middle => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use conways_tui::border_policy::BorderPolicy;
+    use crossterm::event::KeyEventState;
+    use ratatui::prelude::Rect;
+
+    use super::*;
+    use crate::{player_state::PlayerState, zoom::Zoom};
+
+    fn key(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    fn press_and_release(code: KeyCode, app: &mut App) {
+        handle_key_events(key(code, KeyEventKind::Press), app).unwrap();
+        handle_key_events(key(code, KeyEventKind::Release), app).unwrap();
+    }
+
+    #[test]
+    fn escape_leaves_edit_mode_without_quitting() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        app.edit_mode = EditMode::Cursor;
+
+        press_and_release(KeyCode::Esc, &mut app);
+        assert_eq!(app.edit_mode, EditMode::Off);
+        assert!(!app.should_quit);
+
+        press_and_release(KeyCode::Esc, &mut app);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn escape_leaves_edit_mode_then_quits_with_presses_only() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        app.edit_mode = EditMode::Cursor;
+
+        handle_key_events(key(KeyCode::Esc, KeyEventKind::Press), &mut app).unwrap();
+        assert_eq!(app.edit_mode, EditMode::Off);
+        assert!(!app.should_quit);
+
+        handle_key_events(key(KeyCode::Esc, KeyEventKind::Press), &mut app).unwrap();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn q_quits_on_the_press() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        handle_key_events(key(KeyCode::Char('q'), KeyEventKind::Press), &mut app).unwrap();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn escape_cancels_a_line_back_to_the_cursor() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        app.edit_mode = EditMode::Line { anchor: (0, 0) };
+
+        press_and_release(KeyCode::Esc, &mut app);
+        assert_eq!(app.edit_mode, EditMode::Cursor);
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn typed_count_is_stepped_and_pauses() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        app.player_state = PlayerState::Play;
+
+        for code in [KeyCode::Char('2'), KeyCode::Char('5'), KeyCode::Char('.')] {
+            press_and_release(code, &mut app);
+        }
+        assert_eq!(app.simulation.generation(), 25);
+        assert_eq!(app.step_count, None);
+        assert!(matches!(app.player_state, PlayerState::Pause));
+
+        press_and_release(KeyCode::Char('.'), &mut app);
+        assert_eq!(app.simulation.generation(), 26);
+    }
+
+    #[test]
+    fn pan_keys_move_one_character_at_every_zoom() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((16, 16)));
+        app.simulation.set_border_policy(BorderPolicy::Unbounded);
+
+        let mut zoom = Zoom::default();
+        loop {
+            app.set_zoom(zoom);
+            let (cells_x, cells_y) = zoom.cells_per_char();
+            let (x, y) = app.viewport;
+
+            press_and_release(KeyCode::Right, &mut app);
+            press_and_release(KeyCode::Down, &mut app);
+            assert_eq!(app.viewport, (x + cells_x as i64, y + cells_y as i64), "{}", zoom);
+            press_and_release(KeyCode::Char('h'), &mut app);
+            press_and_release(KeyCode::Char('k'), &mut app);
+            assert_eq!(app.viewport, (x, y), "{}", zoom);
+
+            let previous = zoom;
+            zoom.zoom_out();
+            if zoom == previous {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{cell_state::CellState, rule::Rule};
+
+/// Index of a node in the node arena.
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Level of the root of an empty universe.
+const INITIAL_LEVEL: u8 = 3;
+
+/// Amount of nodes above which the nodes no longer used are dropped.
+const MAX_NODES: usize = 4 * 1024 * 1024;
+
+/// A square of `2^level` cells on each side made of four squares of the level
+/// below, or a single cell at level 0.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// North west, north east, south west and south east quadrants.
+    children: [NodeId; 4],
+    level: u8,
+    population: u64,
+}
+
+/// Unbounded universe stored as a quadtree whose identical squares are shared,
+/// computing generations with the HashLife algorithm. The future of every
+/// square is memoised, so repetitive patterns can jump `2^k` generations at once.
+///
+/// Only two state rules with the Moore neighbourhood of radius 1 are supported,
+/// see [`HashLife::supports`]. Cells have no age.
+#[derive(Debug)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    /// Node of each set of four children, so identical squares are stored once.
+    index: HashMap<[NodeId; 4], NodeId>,
+    /// Centre of a node advanced `2^j` generations, by node and `j`.
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// Empty node of each level.
+    empty: Vec<NodeId>,
+    root: NodeId,
+    /// Position of the root top left corner.
+    origin: (i64, i64),
+    /// Birth and survival neighbour counts the memoised results were computed with.
+    rule_masks: (u16, u16),
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashLife {
+    pub fn new() -> Self {
+        let leaf = |population| Node {
+            children: [DEAD; 4],
+            level: 0,
+            population,
+        };
+        let mut hashlife = Self {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin: (0, 0),
+            rule_masks: Self::rule_masks(&Rule::default()),
+        };
+        hashlife.clear();
+        hashlife
+    }
+
+    /// Universe made of the given alive cells.
+    pub fn from_cells(cells: &[(i64, i64)]) -> Self {
+        let mut hashlife = Self::new();
+        hashlife.set_alive_cells(cells);
+        hashlife
+    }
+
+    /// Whether the rule can run on HashLife: two states, the Moore
+    /// neighbourhood of radius 1 and no birth without alive neighbours.
+    pub fn supports(rule: &Rule) -> bool {
+        let offsets = rule.neighbour_offsets();
+        let moore = offsets.len() == 8
+            && offsets
+                .iter()
+                .all(|(dx, dy)| dx.abs() <= 1 && dy.abs() <= 1 && (*dx, *dy) != (0, 0));
+        moore && rule.dying_states() == 0 && !rule.born(0)
+    }
+
+    fn rule_masks(rule: &Rule) -> (u16, u16) {
+        (0..=8).fold((0, 0), |(birth, survival), count| {
+            (
+                birth | (rule.born(count) as u16) << count,
+                survival | (rule.survives(count) as u16) << count,
+            )
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.root = self.empty(INITIAL_LEVEL);
+        let half = 1 << (INITIAL_LEVEL - 1);
+        self.origin = (-half, -half);
+    }
+
+    /// Fills the given rectangle from the origin with random cells, picking the
+    /// same cells as a dense grid of that size for the same seed.
+    pub fn randomize(&mut self, seed: u64, density: f64, width: usize, height: usize) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut cells = Vec::new();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                if rng.gen_bool(density) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        self.set_alive_cells(&cells);
+    }
+
+    /// Replaces the universe content with the given alive cells.
+    pub fn set_alive_cells(&mut self, cells: &[(i64, i64)]) {
+        self.clear();
+        for (x, y) in cells.iter() {
+            self.grow_to(*x, *y);
+        }
+
+        let level = self.nodes[self.root as usize].level;
+        let mut cells = cells
+            .iter()
+            .map(|(x, y)| ((x - self.origin.0) as u64, (y - self.origin.1) as u64))
+            .collect::<Vec<(u64, u64)>>();
+        self.root = self.build(level, &mut cells);
+    }
+
+    /// Builds the node of the given level holding the cells, relative to its corner.
+    fn build(&mut self, level: u8, cells: &mut [(u64, u64)]) -> NodeId {
+        if cells.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+
+        let half = 1 << (level - 1);
+        let mut quadrants: [Vec<(u64, u64)>; 4] = Default::default();
+        for (x, y) in cells.iter() {
+            let quadrant = (*y >= half) as usize * 2 + (*x >= half) as usize;
+            quadrants[quadrant].push((x % half, y % half));
+        }
+        let [nw, ne, sw, se] = quadrants.map(|mut quadrant| self.build(level - 1, &mut quadrant));
+        self.join(nw, ne, sw, se)
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let children = [nw, ne, sw, se];
+        if let Some(id) = self.index.get(&children) {
+            return *id;
+        }
+
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            children,
+            level: self.nodes[nw as usize].level + 1,
+            population: children
+                .iter()
+                .map(|child| self.nodes[*child as usize].population)
+                .sum(),
+        });
+        self.index.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap_or(&DEAD);
+            let id = self.join(below, below, below, below);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    fn size(&self) -> i64 {
+        1 << self.nodes[self.root as usize].level
+    }
+
+    /// Puts the root in the middle of an empty root twice as large.
+    fn expand(&mut self) {
+        let level = self.nodes[self.root as usize].level;
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let nw = self.join(empty, empty, empty, nw);
+        let ne = self.join(empty, empty, ne, empty);
+        let sw = self.join(empty, sw, empty, empty);
+        let se = self.join(se, empty, empty, empty);
+        self.root = self.join(nw, ne, sw, se);
+
+        let quarter = 1 << (level - 1);
+        self.origin = (self.origin.0 - quarter, self.origin.1 - quarter);
+    }
+
+    /// Expands the root until it contains the given cell.
+    fn grow_to(&mut self, x: i64, y: i64) {
+        while x < self.origin.0
+            || y < self.origin.1
+            || x >= self.origin.0 + self.size()
+            || y >= self.origin.1 + self.size()
+        {
+            self.expand();
+        }
+    }
+
+    /// Whether every alive cell of the node is in its central square, half as
+    /// wide as the node.
+    fn is_padded(&self, id: NodeId) -> bool {
+        let population = |id: NodeId| self.nodes[id as usize].population;
+        let [nw, ne, sw, se] = self.children(id);
+        population(nw) == population(self.children(self.children(nw)[3])[3])
+            && population(ne) == population(self.children(self.children(ne)[2])[2])
+            && population(sw) == population(self.children(self.children(sw)[1])[1])
+            && population(se) == population(self.children(self.children(se)[0])[0])
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> &CellState {
+        let (mut x, mut y) = (x - self.origin.0, y - self.origin.1);
+        if x < 0 || y < 0 || x >= self.size() || y >= self.size() {
+            return &CellState::Dead;
+        }
+
+        let mut id = self.root;
+        loop {
+            let node = &self.nodes[id as usize];
+            match (node.population, node.level) {
+                (0, _) => return &CellState::Dead,
+                (_, 0) => return &CellState::Alive(0),
+                (_, level) => {
+                    let half = 1 << (level - 1);
+                    let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+                    id = node.children[quadrant];
+                    (x, y) = (x % half, y % half);
+                }
+            }
+        }
+    }
+
+    /// Sets a cell alive unless the state is dead, the universe having no dying
+    /// states nor ages.
+    pub fn set_cell(&mut self, x: i64, y: i64, state: CellState) {
+        self.grow_to(x, y);
+        let (x, y) = ((x - self.origin.0) as u64, (y - self.origin.1) as u64);
+        let alive = !matches!(state, CellState::Dead);
+        self.root = self.set(self.root, x, y, alive);
+    }
+
+    fn set(&mut self, id: NodeId, x: u64, y: u64, alive: bool) -> NodeId {
+        let node = self.nodes[id as usize];
+        if node.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+
+        let half = 1 << (node.level - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+        let mut children = node.children;
+        children[quadrant] = self.set(children[quadrant], x % half, y % half, alive);
+        let [nw, ne, sw, se] = children;
+        self.join(nw, ne, sw, se)
+    }
+
+    pub fn population(&self) -> usize {
+        self.nodes[self.root as usize].population as usize
+    }
+
+    /// Positions of the alive cells.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        let size = self.size() as usize;
+        self.alive_cells_in(self.origin.0, self.origin.1, size, size)
+    }
+
+    /// Positions of the alive cells inside a rectangle, skipping the empty squares.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        let mut cells = Vec::new();
+        let area = (x, y, x + width as i64, y + height as i64);
+        self.collect_cells(self.root, self.origin, area, &mut cells);
+        cells
+    }
+
+    fn collect_cells(
+        &self,
+        id: NodeId,
+        (x, y): (i64, i64),
+        area: (i64, i64, i64, i64),
+        cells: &mut Vec<(i64, i64)>,
+    ) {
+        let node = &self.nodes[id as usize];
+        let size = 1 << node.level;
+        let (min_x, min_y, max_x, max_y) = area;
+        if node.population == 0
+            || x >= max_x
+            || y >= max_y
+            || x + size <= min_x
+            || y + size <= min_y
+        {
+            return;
+        }
+        if node.level == 0 {
+            cells.push((x, y));
+            return;
+        }
+
+        let half = size / 2;
+        let corners = [(x, y), (x + half, y), (x, y + half), (x + half, y + half)];
+        for (child, corner) in node.children.iter().zip(corners) {
+            self.collect_cells(*child, corner, area, cells);
+        }
+    }
+
+    /// Smallest rectangle containing every alive cell, as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(i64, i64, usize, usize)> {
+        let min_x = self.extent(self.root, 0, false, &mut HashMap::new())? as i64;
+        let max_x = self.extent(self.root, 0, true, &mut HashMap::new())? as i64;
+        let min_y = self.extent(self.root, 1, false, &mut HashMap::new())? as i64;
+        let max_y = self.extent(self.root, 1, true, &mut HashMap::new())? as i64;
+        Some((
+            self.origin.0 + min_x,
+            self.origin.1 + min_y,
+            (max_x - min_x + 1) as usize,
+            (max_y - min_y + 1) as usize,
+        ))
+    }
+
+    /// Lowest or highest coordinate of the alive cells of a node along an axis,
+    /// 0 for x and 1 for y, relative to the node corner.
+    fn extent(
+        &self,
+        id: NodeId,
+        axis: usize,
+        highest: bool,
+        memo: &mut HashMap<NodeId, Option<u64>>,
+    ) -> Option<u64> {
+        let node = self.nodes[id as usize];
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some(0);
+        }
+        if let Some(extent) = memo.get(&id) {
+            return *extent;
+        }
+
+        // Quadrants at the low and high side of the axis.
+        let half = 1 << (node.level - 1);
+        let [nw, ne, sw, se] = node.children;
+        let (low, high) = match axis {
+            0 => ([nw, sw], [ne, se]),
+            _ => ([nw, ne], [sw, se]),
+        };
+        let mut side = |children: [NodeId; 2], offset: u64| {
+            let extents = children.map(|child| self.extent(child, axis, highest, memo));
+            let extents = extents.iter().flatten().map(|extent| extent + offset);
+            match highest {
+                true => extents.max(),
+                false => extents.min(),
+            }
+        };
+        let extent = match highest {
+            true => side(high, half).or_else(|| side(low, 0)),
+            false => side(low, 0).or_else(|| side(high, half)),
+        };
+
+        memo.insert(id, extent);
+        extent
+    }
+
+    /// Computes the given amount of generations, jumping by powers of two.
+    pub fn step(&mut self, rule: &Rule, generations: u64) {
+        for exponent in 0..u64::BITS {
+            if generations & (1 << exponent) != 0 {
+                self.step_pow2(rule, exponent as u8);
+            }
+        }
+    }
+
+    /// Computes `2^exponent` generations at once.
+    pub fn step_pow2(&mut self, rule: &Rule, exponent: u8) {
+        let rule_masks = Self::rule_masks(rule);
+        if rule_masks != self.rule_masks {
+            self.rule_masks = rule_masks;
+            self.results.clear();
+        }
+
+        // The root must be large enough for the patterns to grow for that many
+        // generations without reaching the part of the root that is cut off.
+        while self.nodes[self.root as usize].level < exponent + 3 || !self.is_padded(self.root) {
+            self.expand();
+        }
+
+        let level = self.nodes[self.root as usize].level;
+        self.root = self.successor(self.root, exponent);
+        let quarter = 1 << (level - 2);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+
+        if self.nodes.len() > MAX_NODES {
+            self.collect_garbage();
+        }
+    }
+
+    /// Central square of a node, half as wide, `2^j` generations later.
+    fn successor(&mut self, id: NodeId, j: u8) -> NodeId {
+        let node = self.nodes[id as usize];
+        if node.population == 0 {
+            return self.empty(node.level - 1);
+        }
+        if node.level == 2 {
+            return self.successor_4x4(id);
+        }
+
+        let j = j.min(node.level - 2);
+        if let Some(result) = self.results.get(&(id, j)) {
+            return *result;
+        }
+
+        // Nine overlapping squares half as wide as the node.
+        let [nw, ne, sw, se] = node.children;
+        let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+        let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+        let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+        let [se_nw, se_ne, se_sw, _] = self.children(se);
+        let squares = [
+            nw,
+            self.join(nw_ne, ne_nw, nw_se, ne_sw),
+            ne,
+            self.join(nw_sw, nw_se, sw_nw, sw_ne),
+            self.join(nw_se, ne_sw, sw_ne, se_nw),
+            self.join(ne_sw, ne_se, se_nw, se_ne),
+            sw,
+            self.join(sw_ne, se_nw, sw_se, se_sw),
+            se,
+        ];
+        let c = squares.map(|square| self.successor(square, j));
+
+        let result = if j < node.level - 2 {
+            // Already advanced enough, only the centres are kept.
+            let part = |this: &Self, id: NodeId, quadrant: usize| this.children(id)[quadrant];
+            let quadrants = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]].map(|q| {
+                let nw = part(self, c[q[0]], 3);
+                let ne = part(self, c[q[1]], 2);
+                let sw = part(self, c[q[2]], 1);
+                let se = part(self, c[q[3]], 0);
+                self.join(nw, ne, sw, se)
+            });
+            self.join(quadrants[0], quadrants[1], quadrants[2], quadrants[3])
+        } else {
+            let quadrants = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]].map(|q| {
+                let square = self.join(c[q[0]], c[q[1]], c[q[2]], c[q[3]]);
+                self.successor(square, j)
+            });
+            self.join(quadrants[0], quadrants[1], quadrants[2], quadrants[3])
+        };
+
+        self.results.insert((id, j), result);
+        result
+    }
+
+    /// Central 2x2 cells of a 4x4 node one generation later.
+    fn successor_4x4(&mut self, id: NodeId) -> NodeId {
+        if let Some(result) = self.results.get(&(id, 0)) {
+            return *result;
+        }
+
+        // One bit per cell, row by row.
+        let mut cells = 0u16;
+        for (quadrant, child) in self.children(id).iter().enumerate() {
+            for (cell, leaf) in self.children(*child).iter().enumerate() {
+                let x = (quadrant % 2) * 2 + cell % 2;
+                let y = (quadrant / 2) * 2 + cell / 2;
+                cells |= ((*leaf == ALIVE) as u16) << (y * 4 + x);
+            }
+        }
+
+        let (birth, survival) = self.rule_masks;
+        let next = |x: usize, y: usize| {
+            let alive = |x: usize, y: usize| (cells >> (y * 4 + x)) & 1;
+            let count = alive(x - 1, y - 1)
+                + alive(x, y - 1)
+                + alive(x + 1, y - 1)
+                + alive(x - 1, y)
+                + alive(x + 1, y)
+                + alive(x - 1, y + 1)
+                + alive(x, y + 1)
+                + alive(x + 1, y + 1);
+            let mask = if alive(x, y) == 1 { survival } else { birth };
+            if mask & (1 << count) != 0 {
+                ALIVE
+            } else {
+                DEAD
+            }
+        };
+        let result = self.join(next(1, 1), next(2, 1), next(1, 2), next(2, 2));
+
+        self.results.insert((id, 0), result);
+        result
+    }
+
+    /// Rebuilds the node arena with only the nodes reachable from the root,
+    /// forgetting the memoised results.
+    fn collect_garbage(&mut self) {
+        let cells = self.alive_cells();
+        let (origin, level) = (self.origin, self.nodes[self.root as usize].level);
+
+        *self = Self {
+            rule_masks: self.rule_masks,
+            ..Self::new()
+        };
+        let mut cells = cells
+            .iter()
+            .map(|(x, y)| ((x - origin.0) as u64, (y - origin.1) as u64))
+            .collect::<Vec<(u64, u64)>>();
+        self.root = self.build(level, &mut cells);
+        self.origin = origin;
+    }
+}
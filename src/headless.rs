@@ -0,0 +1,69 @@
+use std::time::Instant;
+
+use conways_tui::{pattern::Pattern, Simulation};
+
+use crate::cli::Cli;
+
+/// Grid size used without a terminal when `--size` is not given.
+const DEFAULT_GRID_SIZE: (usize, usize) = (256, 256);
+
+/// Runs the simulation without a terminal interface for the given amount of
+/// generations, then prints a summary and optionally saves the final state.
+pub fn run(cli: &Cli, pattern: Option<&Pattern>, generations: usize) -> anyhow::Result<()> {
+    let (width, height) = cli.size.unwrap_or(DEFAULT_GRID_SIZE);
+    let mut simulation = Simulation::new(width, height);
+    simulation.set_border_policy(cli.border.clone());
+    simulation.set_threads(cli.thread_count());
+    // Nothing is ever undone without an interface.
+    simulation.history_mut().set_memory_budget(0);
+    // Nor are the cells drawn with the colour of their age.
+    simulation.set_track_ages(false);
+
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    match pattern {
+        Some(pattern) => simulation.load_pattern(pattern, cli.offset),
+        None => simulation.randomize(seed, cli.density),
+    }
+    // An explicit rule takes precedence over the one from the pattern.
+    if let Some(rule) = &cli.rule {
+        simulation.rule = rule.clone();
+    }
+    if cli.hyperspeed {
+        simulation.set_hyperspeed(true)?;
+    }
+
+    let start = Instant::now();
+    simulation.step_n(generations);
+    let elapsed = start.elapsed();
+
+    println!("Rule: {}", simulation.rule);
+    println!(
+        "Grid size: {}x{} ({:?}{})",
+        width,
+        height,
+        simulation.border_policy(),
+        if simulation.is_hyperspeed() { ", hyperspeed" } else { "" }
+    );
+    if pattern.is_none() {
+        println!("Seed: {} (density {})", seed, cli.density);
+    }
+    println!("Generations: {}", generations);
+    println!("Threads: {}", simulation.threads());
+    println!("Population: {}", simulation.population());
+    match simulation.bounding_box() {
+        Some((x, y, w, h)) => println!("Bounding box: {}x{} at ({}, {})", w, h, x, y),
+        None => println!("Bounding box: empty"),
+    }
+    println!(
+        "Elapsed: {:.3}s ({:.1} generations/s)",
+        elapsed.as_secs_f64(),
+        generations as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+
+    if let Some(path) = &cli.output {
+        simulation.save(path)?;
+        println!("Saved {}", path.display());
+    }
+
+    Ok(())
+}
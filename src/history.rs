@@ -0,0 +1,206 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::cell_state::CellState;
+
+/// A cell whose state changed between two states of the universe.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub cell: (i64, i64),
+    pub before: CellState,
+    pub after: CellState,
+}
+
+impl Change {
+    /// Change of a cell over a generation, `None` when there is nothing to
+    /// store: the state is the same, or the cell stayed alive and only aged.
+    pub fn generation(cell: (i64, i64), before: &CellState, after: &CellState) -> Option<Self> {
+        if matches!((before, after), (CellState::Alive(_), CellState::Alive(_))) || before == after
+        {
+            return None;
+        }
+
+        Some(Self {
+            cell,
+            before: before.clone(),
+            after: after.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    /// A generation computed by the rule. Cells alive on both sides are not
+    /// stored as their age only grows by one.
+    Generation,
+    /// Cells set by hand.
+    Edit,
+}
+
+/// The difference between two consecutive states of the universe.
+#[derive(Debug)]
+pub struct Entry {
+    pub kind: EntryKind,
+    pub changes: Vec<Change>,
+}
+
+impl Entry {
+    fn memory(&self) -> usize {
+        std::mem::size_of::<Entry>() + self.changes.len() * std::mem::size_of::<Change>()
+    }
+}
+
+/// Bounded undo and redo stacks of the changes made to the universe, stored as
+/// diffs. The oldest entries are dropped once the memory budget is exceeded.
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    pending_edit: HashMap<(i64, i64), Change>,
+    memory_budget: usize,
+    memory_used: usize,
+}
+
+impl History {
+    /// Creates an empty history, a budget of zero bytes disabling it.
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            pending_edit: HashMap::new(),
+            memory_budget,
+            memory_used: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.memory_budget > 0
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    pub fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = memory_budget;
+        self.evict();
+    }
+
+    /// Bytes taken by the stored entries.
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    /// Amount of generations that can be undone.
+    pub fn rewind_generations(&self) -> usize {
+        self.undo
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::Generation)
+            .count()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.pending_edit.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending_edit.clear();
+        self.memory_used = 0;
+    }
+
+    /// Records the changes of a generation, see [`Change::generation`].
+    pub fn record_generation(&mut self, changes: Vec<Change>) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.commit_edit();
+        self.push(Entry {
+            kind: EntryKind::Generation,
+            changes,
+        });
+    }
+
+    /// Records a cell set by hand. Edits are grouped until [`History::commit_edit`].
+    pub fn record_edit(&mut self, cell: (i64, i64), before: CellState, after: CellState) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.pending_edit
+            .entry(cell)
+            .and_modify(|change| change.after = after.clone())
+            .or_insert(Change {
+                cell,
+                before,
+                after,
+            });
+    }
+
+    /// Closes the group of edits recorded so far into a single entry.
+    pub fn commit_edit(&mut self) {
+        let changes = self
+            .pending_edit
+            .drain()
+            .map(|(_, change)| change)
+            .filter(|change| change.before != change.after)
+            .collect::<Vec<Change>>();
+
+        if !changes.is_empty() {
+            self.push(Entry {
+                kind: EntryKind::Edit,
+                changes,
+            });
+        }
+    }
+
+    /// Takes the most recent entry to undo, it must be given back with
+    /// [`History::push_redo`] once undone.
+    pub fn take_undo(&mut self) -> Option<Entry> {
+        self.commit_edit();
+        let entry = self.undo.pop_back()?;
+        self.memory_used -= entry.memory();
+        Some(entry)
+    }
+
+    pub fn push_redo(&mut self, entry: Entry) {
+        self.memory_used += entry.memory();
+        self.redo.push(entry);
+    }
+
+    /// Takes the most recently undone entry, it must be given back with
+    /// [`History::push_undo`] once redone.
+    pub fn take_redo(&mut self) -> Option<Entry> {
+        let entry = self.redo.pop()?;
+        self.memory_used -= entry.memory();
+        Some(entry)
+    }
+
+    pub fn push_undo(&mut self, entry: Entry) {
+        self.memory_used += entry.memory();
+        self.undo.push_back(entry);
+        self.evict();
+    }
+
+    /// Adds a new entry, forgetting the undone ones as the timeline diverges.
+    fn push(&mut self, entry: Entry) {
+        for redo in self.redo.drain(..) {
+            self.memory_used -= redo.memory();
+        }
+        self.push_undo(entry);
+    }
+
+    fn evict(&mut self) {
+        while self.memory_used > self.memory_budget {
+            match self.undo.pop_front() {
+                Some(entry) => self.memory_used -= entry.memory(),
+                None => break,
+            }
+        }
+    }
+}
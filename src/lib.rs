@@ -0,0 +1,21 @@
+//! Simulation engine for Conway's Game of Life and other Life-like rules.
+//!
+//! The terminal interface is a client of this library, which has no terminal
+//! dependency so other tools can embed the engine directly.
+
+pub mod active_tiles;
+pub mod anchor;
+pub mod border_policy;
+pub mod cell_state;
+pub mod double_buffer_grid;
+pub mod engine;
+pub mod hashlife;
+pub mod history;
+pub mod neighbourhood;
+pub mod packed_grid;
+pub mod pattern;
+pub mod rule;
+pub mod simulation;
+pub mod sparse_grid;
+
+pub use simulation::Simulation;
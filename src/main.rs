@@ -1,47 +1,87 @@
 mod player_state;
-mod cell_state;
-mod border_policy;
+mod speed;
 mod app_layout;
-mod double_buffer_grid;
+mod cli;
+mod drawing;
+mod edit_mode;
 mod app;
 mod app_event;
 mod handler;
+mod headless;
 mod tui;
 mod ui;
+mod zoom;
 
 use app_event::EventHandler;
-use handler::handle_key_events;
+use clap::Parser;
+use cli::Cli;
+use handler::{handle_key_events, handle_mouse_events};
 use ratatui::prelude::*;
 use tui::Tui;
 use std::io::stdout;
 
 use app::App;
+use conways_tui::pattern::Pattern;
+use player_state::PlayerState;
+use speed::Speed;
 
 fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let pattern = match &cli.pattern {
+        Some(path) => Some(Pattern::load(path)?),
+        None => None,
+    };
+
+    if let Some(generations) = cli.headless {
+        return headless::run(&cli, pattern.as_ref(), generations);
+    }
+
     let backend = CrosstermBackend::new(stdout());
     let terminal = Terminal::new(backend)?;
-    let size = terminal.size()?.clone();
-    let input_events = EventHandler::new_input_event_handler(250);
-    let update_events = EventHandler::new_update_event_handler(75);
+    let size = terminal.size()?;
+    let input_events = EventHandler::new_input_event_handler(cli.input_ms);
     let mut tui = Tui::new(terminal, input_events);
     tui.init()?;
 
-    let mut app = App::new(size);
-    app.randomize_cells();
+    let mut app = App::new(size, cli.size);
+    app.resize_anchor = cli.resize_anchor;
+    app.never_shrink = cli.never_shrink;
+    app.simulation.set_threads(cli.thread_count());
+    app.simulation.set_border_policy(cli.border);
+    app.speed = Speed::new(cli.update_ms);
+    app.simulation
+        .history_mut()
+        .set_memory_budget(cli.history_mb * 1024 * 1024);
+    app.density = cli.density;
+    match (&pattern, cli.seed) {
+        (Some(pattern), _) => app.load_pattern(pattern, cli.offset),
+        (None, Some(seed)) => {
+            app.randomize_cells_with_seed(seed);
+        }
+        (None, None) => {
+            app.randomize_cells();
+        }
+    }
+    // An explicit rule takes precedence over the one from the pattern.
+    if let Some(rule) = cli.rule {
+        app.simulation.rule = rule;
+    }
+    if cli.hyperspeed {
+        app.switch_hyperspeed();
+    }
+    if cli.play {
+        app.player_state = PlayerState::Play;
+    }
 
     while !app.should_quit {
         match tui.events.next()? {
             app_event::Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
-            app_event::Event::Mouse(_) => {},
-            app_event::Event::Resize(_, _) => {},
+            app_event::Event::Mouse(mouse_event) => handle_mouse_events(mouse_event, &mut app)?,
+            app_event::Event::Resize(width, height) => app.resize(Rect::new(0, 0, width, height)),
             app_event::Event::None => {},
-            _ => {},
         }
 
-        match update_events.next()? {
-            app_event::Event::Tick => app.tick(),
-            _ => {},
-        }
+        app.tick();
 
         tui.draw(&mut app)?;
     }
@@ -0,0 +1,152 @@
+/// The set of cells, relative to a cell, whose states are counted by a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Neighbourhood {
+    /// Square of the given radius, the classic 8 cell ring for radius 1.
+    Moore(usize),
+    /// Diamond of the given radius (manhattan distance).
+    VonNeumann(usize),
+    /// Hexagonal grid emulated on the square grid by skewing it, ignoring the
+    /// north-east and south-west directions at radius 1.
+    Hexagonal(usize),
+    /// Arbitrary set of offsets, never containing the cell itself.
+    Custom(Vec<(i32, i32)>),
+}
+
+impl Neighbourhood {
+    /// Offsets from a cell to each of its neighbours, the cell itself excluded.
+    pub fn offsets(&self) -> Vec<(i32, i32)> {
+        if let Self::Custom(offsets) = self {
+            return offsets.clone();
+        }
+
+        let radius = self.radius() as i32;
+        let mut offsets = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if (dx, dy) != (0, 0) && self.contains(dx, dy) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+
+    /// Amount of neighbours a cell has.
+    pub fn size(&self) -> usize {
+        self.offsets().len()
+    }
+
+    /// Biggest distance, on either axis, between a cell and its neighbours.
+    pub fn radius(&self) -> usize {
+        match self {
+            Self::Moore(r) | Self::VonNeumann(r) | Self::Hexagonal(r) => *r,
+            Self::Custom(offsets) => offsets
+                .iter()
+                .map(|(dx, dy)| std::cmp::max(dx.unsigned_abs(), dy.unsigned_abs()) as usize)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    fn contains(&self, dx: i32, dy: i32) -> bool {
+        match self {
+            Self::Moore(r) => std::cmp::max(dx.abs(), dy.abs()) as usize <= *r,
+            Self::VonNeumann(r) => (dx.abs() + dy.abs()) as usize <= *r,
+            Self::Hexagonal(r) => {
+                // On the skewed grid moving diagonally NW/SE costs a single step.
+                let distance = if dx.signum() == dy.signum() {
+                    std::cmp::max(dx.abs(), dy.abs())
+                } else {
+                    dx.abs() + dy.abs()
+                };
+                distance as usize <= *r
+            }
+            Self::Custom(offsets) => offsets.contains(&(dx, dy)),
+        }
+    }
+
+    /// Letter used by the `N` field of Larger than Life rulestrings.
+    pub fn letter(&self) -> Option<char> {
+        match self {
+            Self::Moore(_) => Some('M'),
+            Self::VonNeumann(_) => Some('N'),
+            Self::Hexagonal(_) => Some('H'),
+            Self::Custom(_) => None,
+        }
+    }
+
+    pub fn from_letter(letter: char, radius: usize) -> anyhow::Result<Self> {
+        match letter.to_ascii_uppercase() {
+            'M' => Ok(Self::Moore(radius)),
+            'N' => Ok(Self::VonNeumann(radius)),
+            'H' => Ok(Self::Hexagonal(radius)),
+            _ => anyhow::bail!("Unknown neighbourhood '{}'", letter),
+        }
+    }
+
+    /// Builds a custom neighbourhood from a hexadecimal bit mask covering the
+    /// `(2r + 1)^2` square around the cell, row by row from the top left and
+    /// most significant bit first. The centre bit is ignored, and at least one
+    /// other bit must be set.
+    pub fn from_mask(radius: usize, mask: &str) -> anyhow::Result<Self> {
+        let side = 2 * radius + 1;
+        let bits = mask
+            .chars()
+            .map(|c| c.to_digit(16))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(|| anyhow::anyhow!("Invalid neighbourhood mask '{}'", mask))?
+            .into_iter()
+            .flat_map(|nibble| (0..4).rev().map(move |bit| nibble & (1 << bit) != 0))
+            .collect::<Vec<bool>>();
+
+        if bits.len() < side * side {
+            anyhow::bail!(
+                "Neighbourhood mask '{}' is too short for radius {}",
+                mask,
+                radius
+            );
+        }
+
+        let r = radius as i32;
+        let offsets = bits
+            .iter()
+            .take(side * side)
+            .enumerate()
+            .filter(|(_, set)| **set)
+            .map(|(i, _)| ((i % side) as i32 - r, (i / side) as i32 - r))
+            .filter(|offset| *offset != (0, 0))
+            .collect::<Vec<_>>();
+
+        // Without neighbours the radius would be lost when written back.
+        if offsets.is_empty() {
+            anyhow::bail!("Neighbourhood mask '{}' has no neighbours", mask);
+        }
+        Ok(Self::Custom(offsets))
+    }
+
+    /// Inverse of [`Neighbourhood::from_mask`].
+    pub fn to_mask(&self) -> String {
+        let r = self.radius() as i32;
+        let mut bits = Vec::new();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                bits.push((dx, dy) != (0, 0) && self.contains(dx, dy));
+            }
+        }
+
+        bits.chunks(4)
+            .map(|chunk| {
+                let nibble = (0..4).fold(0, |acc, i| {
+                    (acc << 1) | chunk.get(i).copied().unwrap_or(false) as u32
+                });
+                std::char::from_digit(nibble, 16).unwrap_or('0')
+            })
+            .collect()
+    }
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Self {
+        Self::Moore(1)
+    }
+}
@@ -0,0 +1,456 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    active_tiles::ActiveTiles, anchor::Anchor, border_policy::BorderPolicy, cell_state::CellState,
+    history::Change, rule::Rule,
+};
+
+/// Cells stored in each word.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Rows of the tiles whose activity is tracked, a word wide.
+const TILE_ROWS: usize = 16;
+
+/// Grid storing one bit per cell, row by row, computing generations a word of
+/// 64 cells at a time with bitwise neighbour counting.
+///
+/// Only two state rules with the Moore neighbourhood of radius 1 are supported,
+/// see [`PackedGrid::supports`]. Cell ages are kept in an optional side buffer.
+#[derive(Debug, Clone)]
+pub struct PackedGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    cells: Vec<u64>,
+    /// Cells of the previous generation, to tell what the last step changed.
+    previous: Vec<u64>,
+    /// Age of each cell when tracked, only meaningful for the alive ones.
+    ages: Option<Vec<usize>>,
+    tiles: ActiveTiles,
+}
+
+impl PackedGrid {
+    pub fn new(width: usize, height: usize, track_ages: bool) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS);
+        Self {
+            width,
+            height,
+            words_per_row,
+            cells: vec![0; words_per_row * height],
+            previous: vec![0; words_per_row * height],
+            ages: track_ages.then(|| vec![0; width * height]),
+            tiles: ActiveTiles::new(width, height, WORD_BITS, TILE_ROWS),
+        }
+    }
+
+    /// Whether the rule can run on a packed grid: two states and the Moore
+    /// neighbourhood of radius 1.
+    pub fn supports(rule: &Rule) -> bool {
+        let offsets = rule.neighbour_offsets();
+        let moore = offsets.len() == 8
+            && offsets
+                .iter()
+                .all(|(dx, dy)| dx.abs() <= 1 && dy.abs() <= 1 && (*dx, *dy) != (0, 0));
+        moore && rule.dying_states() == 0
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Tiles computed on the next step.
+    pub fn tiles(&self) -> &ActiveTiles {
+        &self.tiles
+    }
+
+    /// Whether the age of the cells is kept, cells being aged 0 otherwise.
+    pub fn tracks_ages(&self) -> bool {
+        self.ages.is_some()
+    }
+
+    /// Word holding a cell and the bit of the cell in it.
+    fn position(&self, x: usize, y: usize) -> (usize, u64) {
+        (y * self.words_per_row + x / WORD_BITS, 1 << (x % WORD_BITS))
+    }
+
+    fn is_alive(&self, x: usize, y: usize) -> bool {
+        let (word, bit) = self.position(x, y);
+        self.cells[word] & bit != 0
+    }
+
+    /// Changes the grid size, keeping the cells in place around the anchor.
+    /// Cells falling outside the new size are dropped.
+    pub fn resize(&mut self, width: usize, height: usize, anchor: Anchor) {
+        let offset_x = anchor.offset(self.width, width) as i64;
+        let offset_y = anchor.offset(self.height, height) as i64;
+
+        let mut resized = Self::new(width, height, self.tracks_ages());
+        for ((x, y), state) in self.cells() {
+            let (x, y) = (x + offset_x, y + offset_y);
+            if x >= 0 && y >= 0 && x < width as i64 && y < height as i64 {
+                resized.set_cell(x as usize, y as usize, state);
+            }
+        }
+        *self = resized;
+    }
+
+    /// Fills the grid with random cells, picking the same cells as a dense grid
+    /// of that size for the same seed.
+    pub fn randomize(&mut self, seed: u64, density: f64) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        self.clear();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if rng.gen_bool(density) {
+                    let (word, bit) = self.position(x, y);
+                    self.cells[word] |= bit;
+                }
+            }
+        }
+        self.previous.copy_from_slice(&self.cells);
+        self.tiles.mark_all();
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(0);
+        self.previous.fill(0);
+        if let Some(ages) = &mut self.ages {
+            ages.fill(0);
+        }
+        self.tiles.mark_all();
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> CellState {
+        match self.is_alive(x, y) {
+            true => CellState::Alive(self.age(x, y)),
+            false => CellState::Dead,
+        }
+    }
+
+    fn age(&self, x: usize, y: usize) -> usize {
+        self.ages
+            .as_ref()
+            .map_or(0, |ages| ages[y * self.width + x])
+    }
+
+    /// Sets a cell in the current and previous generation, so it is not seen as
+    /// changed by the last step. Dying states are stored as dead cells.
+    pub fn set_cell(&mut self, x: usize, y: usize, state: CellState) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let (word, bit) = self.position(x, y);
+        match state {
+            CellState::Alive(age) => {
+                self.cells[word] |= bit;
+                self.previous[word] |= bit;
+                if let Some(ages) = &mut self.ages {
+                    ages[y * self.width + x] = age;
+                }
+            }
+            CellState::Dying(_) | CellState::Dead => {
+                self.cells[word] &= !bit;
+                self.previous[word] &= !bit;
+            }
+        }
+        self.tiles.mark_cell(x, y);
+    }
+
+    /// Changes the age of every alive cell, when ages are tracked.
+    pub fn age_cells(&mut self, delta: isize) {
+        for (x, y) in self.alive_positions() {
+            if let Some(ages) = &mut self.ages {
+                let age = &mut ages[y * self.width + x];
+                *age = age.saturating_add_signed(delta);
+            }
+        }
+    }
+
+    /// Amount of alive cells.
+    pub fn population(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Smallest rectangle containing every alive cell, as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let alive = self.alive_positions();
+        let min_x = alive.iter().map(|(x, _)| *x).min()?;
+        let min_y = alive.iter().map(|(_, y)| *y).min()?;
+        let max_x = alive.iter().map(|(x, _)| *x).max()?;
+        let max_y = alive.iter().map(|(_, y)| *y).max()?;
+        Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    /// Positions of the alive cells, skipping the empty words.
+    fn alive_positions(&self) -> Vec<(usize, usize)> {
+        let mut alive = Vec::new();
+        for (index, word) in self.cells.iter().enumerate() {
+            let (y, first_x) = (
+                index / self.words_per_row,
+                (index % self.words_per_row) * WORD_BITS,
+            );
+            let mut word = *word;
+            while word != 0 {
+                alive.push((first_x + word.trailing_zeros() as usize, y));
+                word &= word - 1;
+            }
+        }
+        alive
+    }
+
+    /// Cells that are not dead, with their state.
+    pub fn cells(&self) -> Vec<((i64, i64), CellState)> {
+        self.alive_positions()
+            .into_iter()
+            .map(|(x, y)| ((x as i64, y as i64), CellState::Alive(self.age(x, y))))
+            .collect()
+    }
+
+    /// Positions of the alive cells.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        self.alive_positions()
+            .into_iter()
+            .map(|(x, y)| (x as i64, y as i64))
+            .collect()
+    }
+
+    /// Positions of the alive cells inside a rectangle.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        let min_x = x.clamp(0, self.width as i64) as usize;
+        let min_y = y.clamp(0, self.height as i64) as usize;
+        let max_x = (x + width as i64).clamp(0, self.width as i64) as usize;
+        let max_y = (y + height as i64).clamp(0, self.height as i64) as usize;
+
+        let mut alive = Vec::new();
+        for cell_y in min_y..max_y {
+            for cell_x in min_x..max_x {
+                if self.is_alive(cell_x, cell_y) {
+                    alive.push((cell_x as i64, cell_y as i64));
+                }
+            }
+        }
+        alive
+    }
+
+    /// Computes the next generation, counting the neighbours of 64 cells at once.
+    pub fn step(&mut self, rule: &Rule, policy: BorderPolicy) {
+        self.step_in_bands(rule, policy, 1);
+    }
+
+    /// Computes the next generation with the rows split in bands, each computed
+    /// on its own thread. The cells are the same whatever the amount of threads.
+    /// Only the active tiles are computed, the others keep their cells.
+    pub fn step_in_bands(&mut self, rule: &Rule, policy: BorderPolicy, threads: usize) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        self.tiles.start_step(rule, &policy);
+        let rule_masks = (0..=8).fold((0u16, 0u16), |(born, survives), count| {
+            (
+                born | (rule.born(count) as u16) << count,
+                survives | (rule.survives(count) as u16) << count,
+            )
+        });
+
+        let mut next = std::mem::take(&mut self.previous);
+        let band_rows = self.height.div_ceil(threads.max(1));
+        if threads <= 1 {
+            self.step_rows(0, &mut next, rule_masks, &policy);
+        } else {
+            let (this, policy) = (&*self, &policy);
+            std::thread::scope(|scope| {
+                let bands = next.chunks_mut(band_rows * self.words_per_row);
+                for (band, words) in bands.enumerate() {
+                    let first_row = band * band_rows;
+                    scope.spawn(move || this.step_rows(first_row, words, rule_masks, policy));
+                }
+            });
+        }
+
+        self.previous = std::mem::replace(&mut self.cells, next);
+        self.update_ages();
+        self.tiles.finish_step(&self.changed_tiles());
+    }
+
+    /// Tiles where a cell was born or died in the last step.
+    fn changed_tiles(&self) -> Vec<bool> {
+        let columns = self.tiles.columns();
+        let mut changed = vec![false; columns * self.height.div_ceil(TILE_ROWS)];
+        let words = self.previous.iter().zip(self.cells.iter());
+        for (index, (before, after)) in words.enumerate() {
+            if before != after {
+                let row = index / self.words_per_row / TILE_ROWS;
+                changed[row * columns + index % self.words_per_row] = true;
+            }
+        }
+        changed
+    }
+
+    /// Computes the rows from `first_row` into `next`, which holds whole rows,
+    /// reading the neighbours from the current generation.
+    fn step_rows(
+        &self,
+        first_row: usize,
+        next: &mut [u64],
+        (born, survives): (u16, u16),
+        policy: &BorderPolicy,
+    ) {
+        let wrap = *policy == BorderPolicy::Wrap;
+        // Bits past the grid width in the last word of each row stay dead.
+        let last_word_mask = match self.width % WORD_BITS {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        };
+
+        let (width, height, words_per_row) = (self.width, self.height, self.words_per_row);
+        let empty_row = vec![0; words_per_row];
+        let row = |y: usize| &self.cells[y * words_per_row..(y + 1) * words_per_row];
+
+        for (band_y, next_row) in next.chunks_mut(words_per_row).enumerate() {
+            let y = first_row + band_y;
+            let above = match y {
+                0 if wrap => row(height - 1),
+                0 => &empty_row,
+                _ => row(y - 1),
+            };
+            let below = match y + 1 {
+                next_y if next_y < height => row(next_y),
+                _ if wrap => row(0),
+                _ => &empty_row,
+            };
+            let current = row(y);
+
+            for (word, result) in next_row.iter_mut().enumerate() {
+                if !self.tiles.is_tile_active(word, y / TILE_ROWS) {
+                    *result = current[word];
+                    continue;
+                }
+                let neighbours = [
+                    west(above, word, wrap, width),
+                    above[word],
+                    east(above, word, wrap, width),
+                    west(current, word, wrap, width),
+                    east(current, word, wrap, width),
+                    west(below, word, wrap, width),
+                    below[word],
+                    east(below, word, wrap, width),
+                ];
+                *result = next_word(current[word], neighbours, born, survives);
+                if word + 1 == words_per_row {
+                    *result &= last_word_mask;
+                }
+            }
+        }
+    }
+
+    /// Ages the cells that survived the last step and resets the born ones.
+    fn update_ages(&mut self) {
+        let Some(ages) = &mut self.ages else {
+            return;
+        };
+
+        for (index, (before, after)) in self.previous.iter().zip(self.cells.iter()).enumerate() {
+            let (y, first_x) = (
+                index / self.words_per_row,
+                (index % self.words_per_row) * WORD_BITS,
+            );
+            let mut alive = *after;
+            while alive != 0 {
+                let bit = alive.trailing_zeros() as usize;
+                let age = &mut ages[y * self.width + first_x + bit];
+                *age = match before & (1 << bit) {
+                    0 => 0,
+                    _ => *age + 1,
+                };
+                alive &= alive - 1;
+            }
+        }
+    }
+
+    /// Cells changed by the last step, see [`Change::generation`].
+    pub fn changes(&self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (index, (before, after)) in self.previous.iter().zip(self.cells.iter()).enumerate() {
+            let (y, first_x) = (
+                index / self.words_per_row,
+                (index % self.words_per_row) * WORD_BITS,
+            );
+            let mut changed = before ^ after;
+            while changed != 0 {
+                let bit = changed.trailing_zeros() as usize;
+                let x = first_x + bit;
+                let state = CellState::Alive(self.age(x, y));
+                let (before, after) = match after & (1 << bit) {
+                    0 => (state, CellState::Dead),
+                    _ => (CellState::Dead, state),
+                };
+                changes.extend(Change::generation((x as i64, y as i64), &before, &after));
+                changed &= changed - 1;
+            }
+        }
+        changes
+    }
+}
+
+/// Word of a row where each bit holds the cell west of the bit position.
+fn west(row: &[u64], word: usize, wrap: bool, width: usize) -> u64 {
+    let carry = match word {
+        0 if wrap => (row[(width - 1) / WORD_BITS] >> ((width - 1) % WORD_BITS)) & 1,
+        0 => 0,
+        _ => row[word - 1] >> (WORD_BITS - 1),
+    };
+    (row[word] << 1) | carry
+}
+
+/// Word of a row where each bit holds the cell east of the bit position.
+fn east(row: &[u64], word: usize, wrap: bool, width: usize) -> u64 {
+    if word + 1 < row.len() {
+        return (row[word] >> 1) | (row[word + 1] << (WORD_BITS - 1));
+    }
+    // The cell east of the last one is past the grid width, or the first one when wrapping.
+    let carry = if wrap { row[0] & 1 } else { 0 };
+    (row[word] >> 1) | (carry << ((width - 1) % WORD_BITS))
+}
+
+/// Next state of a word of cells from their eight neighbour words, the counts
+/// being added bitwise so every cell of the word is computed at once.
+fn next_word(alive: u64, neighbours: [u64; 8], born: u16, survives: u16) -> u64 {
+    let full_add = |a: u64, b: u64, c: u64| (a ^ b ^ c, (a & b) | ((a ^ b) & c));
+    let [nw, n, ne, w, e, sw, s, se] = neighbours;
+
+    // Each bit of the count, adding the ones then the carries of each weight.
+    let (ones_a, twos_a) = full_add(nw, n, ne);
+    let (ones_b, twos_b) = full_add(w, e, sw);
+    let (ones_c, twos_c) = (s ^ se, s & se);
+    let (count_1, twos_d) = full_add(ones_a, ones_b, ones_c);
+    let (twos, fours_a) = full_add(twos_a, twos_b, twos_c);
+    let (count_2, fours_b) = (twos ^ twos_d, twos & twos_d);
+    let (count_4, count_8) = (fours_a ^ fours_b, fours_a & fours_b);
+
+    let mut next = 0;
+    for count in 0..=8 {
+        let (births, survivals) = (born & (1 << count) != 0, survives & (1 << count) != 0);
+        if !births && !survivals {
+            continue;
+        }
+
+        let bit = |word: u64, weight: usize| if count & weight != 0 { word } else { !word };
+        let matches = bit(count_1, 1) & bit(count_2, 2) & bit(count_4, 4) & bit(count_8, 8);
+        if births {
+            next |= matches & !alive;
+        }
+        if survivals {
+            next |= matches & alive;
+        }
+    }
+    next
+}
@@ -0,0 +1,110 @@
+pub mod cells;
+pub mod life106;
+pub mod rle;
+
+use std::path::Path;
+
+use crate::rule::Rule;
+
+/// A set of alive cells, positioned relative to the top left corner of the
+/// pattern bounding box, together with the rule it was designed for.
+#[derive(Debug, Clone, Default)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>,
+    pub rule: Option<Rule>,
+    pub name: Option<String>,
+    /// Free form comment lines, `#C` lines in RLE.
+    pub comments: Vec<String>,
+}
+
+/// File formats patterns can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternFormat {
+    /// Run Length Encoded, `.rle`.
+    Rle,
+    /// Plaintext, `.cells`.
+    Plaintext,
+    /// Life 1.06 coordinate list, `.lif` or `.life`.
+    Life106,
+}
+
+impl PatternFormat {
+    /// Picks the format from the file extension.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("rle") => Ok(Self::Rle),
+            Some("cells") => Ok(Self::Plaintext),
+            Some("lif" | "life") => Ok(Self::Life106),
+            _ => anyhow::bail!(
+                "Unknown pattern format for '{}', expected .rle, .cells, .lif or .life",
+                path.display()
+            ),
+        }
+    }
+
+    pub fn read(&self, content: &str) -> anyhow::Result<Pattern> {
+        match self {
+            Self::Rle => rle::read(content),
+            Self::Plaintext => cells::read(content),
+            Self::Life106 => life106::read(content),
+        }
+    }
+
+    pub fn write(&self, pattern: &Pattern) -> String {
+        match self {
+            Self::Rle => rle::write(pattern),
+            Self::Plaintext => cells::write(pattern),
+            Self::Life106 => life106::write(pattern),
+        }
+    }
+}
+
+impl Pattern {
+    /// Builds a pattern from alive cells at arbitrary coordinates, moving them
+    /// so the top left corner of their bounding box sits at the origin.
+    pub fn from_cells(cells: &[(i64, i64)]) -> Self {
+        let min_x = cells.iter().map(|(x, _)| *x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|(_, y)| *y).min().unwrap_or(0);
+        let max_x = cells.iter().map(|(x, _)| *x + 1).max().unwrap_or(0);
+        let max_y = cells.iter().map(|(_, y)| *y + 1).max().unwrap_or(0);
+
+        Self {
+            width: (max_x - min_x) as usize,
+            height: (max_y - min_y) as usize,
+            cells: cells
+                .iter()
+                .map(|(x, y)| ((x - min_x) as usize, (y - min_y) as usize))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Grows the pattern size if needed so every cell fits in it.
+    fn fit_cells(&mut self) {
+        for (x, y) in self.cells.iter() {
+            self.width = std::cmp::max(self.width, x + 1);
+            self.height = std::cmp::max(self.height, y + 1);
+        }
+    }
+
+    /// Reads a pattern file from disk, the format being picked from its extension.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let format = PatternFormat::from_path(path)?;
+        let content = std::fs::read_to_string(path)?;
+        format.read(&content)
+    }
+
+    /// Writes the pattern to disk, the format being picked from the extension.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let format = PatternFormat::from_path(path)?;
+        std::fs::write(path, format.write(self))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use super::Pattern;
+
+/// Parses a pattern in the plaintext format, `O` for alive cells and `.` for
+/// dead ones, with `!` comment lines.
+pub fn read(content: &str) -> anyhow::Result<Pattern> {
+    let mut pattern = Pattern::default();
+
+    let mut y = 0;
+    for line in content.lines().map(str::trim_end) {
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                'O' | '*' => pattern.cells.push((x, y)),
+                '.' => {}
+                _ => anyhow::bail!("Unexpected character '{}' in plaintext pattern", c),
+            }
+        }
+        y += 1;
+    }
+
+    pattern.fit_cells();
+    Ok(pattern)
+}
+
+/// Encodes a pattern in the plaintext format.
+pub fn write(pattern: &Pattern) -> String {
+    let mut content = String::new();
+    if let Some(name) = &pattern.name {
+        content.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in pattern.comments.iter() {
+        content.push_str(&format!("!{}\n", comment));
+    }
+
+    let mut rows = vec![vec!['.'; pattern.width]; pattern.height];
+    for (x, y) in pattern.cells.iter() {
+        rows[*y][*x] = 'O';
+    }
+    for row in rows {
+        content.extend(row);
+        content.push('\n');
+    }
+
+    content
+}
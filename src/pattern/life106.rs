@@ -0,0 +1,77 @@
+use anyhow::Context;
+
+use super::Pattern;
+
+const HEADER: &str = "#Life 1.06";
+
+/// Parses a pattern in the Life 1.06 format, one `x y` coordinate pair per
+/// alive cell.
+pub fn read(content: &str) -> anyhow::Result<Pattern> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    match lines.next() {
+        Some(HEADER) => {}
+        Some(header) => anyhow::bail!("Unsupported Life format '{}', expected {}", header, HEADER),
+        None => anyhow::bail!("Life 1.06 pattern is missing its header"),
+    }
+
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut cells = Vec::new();
+    for line in lines {
+        if let Some(line) = line.strip_prefix('#') {
+            let mut chars = line.chars();
+            match (chars.next(), chars.as_str()) {
+                (Some('N'), value) => name = Some(value.trim().to_string()),
+                (Some('D' | 'C'), value) => comments.push(value.trim().to_string()),
+                _ => {}
+            }
+            continue;
+        }
+
+        let (x, y) = line
+            .split_once(char::is_whitespace)
+            .with_context(|| format!("Invalid Life 1.06 coordinates '{}'", line))?;
+        let x = x.trim().parse::<i64>()?;
+        let y = y.trim().parse::<i64>()?;
+        cells.push((x, y));
+    }
+
+    let mut pattern = Pattern::from_cells(&cells);
+    pattern.name = name;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+/// Encodes a pattern in the Life 1.06 format.
+pub fn write(pattern: &Pattern) -> String {
+    let mut content = format!("{}\n", HEADER);
+    for (x, y) in pattern.cells.iter() {
+        content.push_str(&format!("{} {}\n", x, y));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_lines_are_read() {
+        let pattern =
+            read("#Life 1.06\n#N Glider\n#D A small ship\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, vec!["A small ship".to_string()]);
+        assert_eq!(pattern.cells.len(), 5);
+    }
+
+    #[test]
+    fn non_ascii_header_lines_do_not_panic() {
+        let pattern = read("#Life 1.06\n#é\n#Dété\n#\n0 0\n").unwrap();
+        assert_eq!(pattern.comments, vec!["été".to_string()]);
+        assert_eq!(pattern.cells.len(), 1);
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Context;
+
+use super::Pattern;
+
+/// Maximum length of the body lines written, as recommended by the format.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Cells read past the header size, which some writers get slightly wrong.
+const SIZE_SLACK: usize = 1024;
+
+/// Parses a pattern in the Run Length Encoded format.
+///
+/// Only the alive state is kept, any other state of multi-state patterns is
+/// read as dead.
+pub fn read(content: &str) -> anyhow::Result<Pattern> {
+    let comments = content
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| line.strip_prefix("#C").or_else(|| line.strip_prefix("#c")))
+        .map(|comment| comment.trim().to_string())
+        .collect();
+    let name = content
+        .lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("#N"))
+        .map(|name| name.trim().to_string());
+
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let header = lines.next().context("RLE pattern is missing its header")?;
+    let mut pattern = parse_header(header)?;
+    pattern.name = name;
+    pattern.comments = comments;
+
+    let (limit_x, limit_y) = (pattern.width + SIZE_SLACK, pattern.height + SIZE_SLACK);
+    let (mut x, mut y) = (0, 0);
+    let mut count = None::<usize>;
+    'body: for line in lines {
+        for c in line.chars() {
+            let run = count.unwrap_or(1);
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit));
+                    if count.is_none() {
+                        anyhow::bail!("Run count too large in RLE pattern");
+                    }
+                    continue;
+                }
+                '!' => break 'body,
+                '$' => {
+                    y = advance(y, run, limit_y)?;
+                    x = 0;
+                }
+                'b' | '.' => x = advance(x, run, limit_x)?,
+                'o' | 'A' => {
+                    let end = advance(x, run, limit_x)?;
+                    pattern.cells.extend((x..end).map(|x| (x, y)));
+                    x = end;
+                }
+                c if c.is_ascii_alphabetic() => x = advance(x, run, limit_x)?,
+                c if c.is_whitespace() => continue,
+                _ => anyhow::bail!("Unexpected character '{}' in RLE pattern", c),
+            }
+            count = None;
+        }
+    }
+
+    pattern.fit_cells();
+    Ok(pattern)
+}
+
+/// Moves a position along a run, failing far past the header size.
+fn advance(position: usize, run: usize, limit: usize) -> anyhow::Result<usize> {
+    position
+        .checked_add(run)
+        .filter(|end| *end <= limit)
+        .context("RLE pattern runs far beyond the size in its header")
+}
+
+/// Encodes a pattern in the Run Length Encoded format.
+pub fn write(pattern: &Pattern) -> String {
+    let mut content = String::new();
+    if let Some(name) = &pattern.name {
+        content.push_str(&format!("#N {}\n", name));
+    }
+    for comment in pattern.comments.iter() {
+        content.push_str(&format!("#C {}\n", comment));
+    }
+
+    content.push_str(&format!("x = {}, y = {}", pattern.width, pattern.height));
+    if let Some(rule) = &pattern.rule {
+        content.push_str(&format!(", rule = {}", rule));
+    }
+    content.push('\n');
+
+    let mut rows = vec![Vec::new(); pattern.height];
+    for (x, y) in pattern.cells.iter() {
+        rows[*y].push(*x);
+    }
+
+    // Runs of (count, tag), trailing dead cells of each row are left out.
+    let mut runs = Vec::<(usize, char)>::new();
+    let mut push_run = |count: usize, tag: char| match runs.last_mut() {
+        Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+        _ => runs.push((count, tag)),
+    };
+    for (y, row) in rows.iter_mut().enumerate() {
+        if y > 0 {
+            push_run(1, '$');
+        }
+        row.sort_unstable();
+        row.dedup();
+        let mut x = 0;
+        for cell_x in row.iter() {
+            if *cell_x > x {
+                push_run(cell_x - x, 'b');
+            }
+            push_run(1, 'o');
+            x = cell_x + 1;
+        }
+    }
+    if let Some((_, '$')) = runs.last() {
+        runs.pop();
+    }
+    runs.push((1, '!'));
+
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let run = match count {
+            1 => tag.to_string(),
+            _ => format!("{}{}", count, tag),
+        };
+        if line.len() + run.len() > MAX_LINE_LENGTH {
+            content.push_str(&line);
+            content.push('\n');
+            line.clear();
+        }
+        line.push_str(&run);
+    }
+    content.push_str(&line);
+    content.push('\n');
+
+    content
+}
+
+/// Parses the `x = 3, y = 3, rule = B3/S23` header line, the rule being optional.
+fn parse_header(header: &str) -> anyhow::Result<Pattern> {
+    let mut pattern = Pattern::default();
+
+    // Larger than Life rules contain commas, so the rule takes the rest of the line.
+    let (sizes, rule) = match header.find("rule") {
+        Some(index) => (&header[..index], Some(&header[index + "rule".len()..])),
+        None => (header, None),
+    };
+
+    for field in sizes.split(',').filter(|field| !field.trim().is_empty()) {
+        let (key, value) = field
+            .split_once('=')
+            .with_context(|| format!("Invalid RLE header field '{}'", field))?;
+        let value = value
+            .trim()
+            .parse::<usize>()
+            .with_context(|| format!("Invalid RLE header field '{}'", field))?;
+        match key.trim() {
+            "x" => pattern.width = value,
+            "y" => pattern.height = value,
+            _ => {}
+        }
+    }
+
+    if let Some(rule) = rule {
+        let rule = rule.trim_start().trim_start_matches('=').trim();
+        // Golly appends the bounded grid size after a colon, e.g. "B3/S23:T100,100".
+        let rule = rule.split(':').next().unwrap_or(rule);
+        pattern.rule = Some(
+            rule.parse()
+                .with_context(|| format!("Invalid rule '{}' in RLE header", rule))?,
+        );
+    }
+
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#N Glider\n#C A small ship\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+
+    fn sorted(mut cells: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn glider_is_read() {
+        let pattern = read(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, vec!["A small ship".to_string()]);
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.rule, Some("B3/S23".parse().unwrap()));
+        assert_eq!(
+            sorted(pattern.cells),
+            vec![(0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn runs_span_lines_and_stop_at_the_end_mark() {
+        let pattern = read("x = 12, y = 2\n1\n2o$\n\n3bo!\n5o\n").unwrap();
+        let mut expected: Vec<_> = (0..12).map(|x| (x, 0)).collect();
+        expected.push((3, 1));
+        assert_eq!(sorted(pattern.cells), sorted(expected));
+    }
+
+    #[test]
+    fn cells_slightly_past_the_header_size_grow_it() {
+        let pattern = read("x = 1, y = 1\n3o$o!\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 2));
+    }
+
+    #[test]
+    fn missing_header_and_unexpected_characters_are_rejected() {
+        assert!(read("").is_err());
+        assert!(read("#C only a comment\n").is_err());
+        assert!(read("x = 3, y = 3\nb?o!\n").is_err());
+    }
+
+    #[test]
+    fn overflowing_run_counts_are_rejected() {
+        assert!(read("x = 3, y = 3\n99999999999999999999o!\n").is_err());
+        assert!(read("x = 3, y = 3\n99999999999999999999$o!\n").is_err());
+    }
+
+    #[test]
+    fn runs_far_beyond_the_header_size_are_rejected() {
+        assert!(read("x = 3, y = 3\n4000000000o!\n").is_err());
+        assert!(read("x = 3, y = 3\n4000000000bo!\n").is_err());
+        assert!(read("x = 3, y = 3\n4000000000$o!\n").is_err());
+    }
+
+    #[test]
+    fn written_patterns_are_read_back() {
+        let mut pattern = read(GLIDER).unwrap();
+        // Long enough rows for the body to be split over several lines.
+        pattern.cells.extend((0..200).step_by(3).map(|x| (x, 5)));
+        pattern.cells.push((199, 7));
+        pattern.width = 200;
+        pattern.height = 8;
+
+        let content = write(&pattern);
+        assert!(content.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        let read_back = read(&content).unwrap();
+        assert_eq!(read_back.name, pattern.name);
+        assert_eq!(read_back.comments, pattern.comments);
+        assert_eq!(read_back.rule, pattern.rule);
+        assert_eq!((read_back.width, read_back.height), (200, 8));
+        assert_eq!(sorted(read_back.cells), sorted(pattern.cells));
+    }
+}
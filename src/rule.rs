@@ -0,0 +1,433 @@
+use std::{fmt, str::FromStr};
+
+use crate::{cell_state::CellState, neighbourhood::Neighbourhood};
+
+/// Well known Life-like rules that can be cycled through at runtime.
+pub const RULE_PRESETS: [(&str, &str); 14] = [
+    ("Conway's Life", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Day & Night", "B3678/S34678"),
+    ("Seeds", "B2/S"),
+    ("Maze", "B3/S12345"),
+    ("Life without Death", "B3/S012345678"),
+    ("2x2", "B36/S125"),
+    ("Replicator", "B1357/S1357"),
+    ("Brian's Brain", "B2/S/C3"),
+    ("Star Wars", "B2/S345/C4"),
+    ("Frogs", "B34/S12/C3"),
+    ("Hexagonal Life", "B2/S34H"),
+    ("Bosco's Rule", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("Majority", "R4,C0,M1,S41..81,B41..81,NM"),
+];
+
+/// Biggest neighbourhood radius accepted from a rulestring.
+const MAX_RADIUS: usize = 50;
+
+/// A Life-like (outer totalistic) rule, described by the neighbour counts
+/// that make a dead cell be born and an alive cell survive.
+///
+/// Generations rules add a state count: cells that fail to survive go
+/// through `states - 2` dying states before becoming dead.
+///
+/// Larger than Life rules use a wider neighbourhood, that can also count
+/// the cell itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    states: usize,
+    neighbourhood: Neighbourhood,
+    include_centre: bool,
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub fn conway() -> Self {
+        let mut rule = Self {
+            birth: vec![false; 9],
+            survival: vec![false; 9],
+            states: 2,
+            neighbourhood: Neighbourhood::Moore(1),
+            include_centre: false,
+        };
+        rule.birth[3] = true;
+        rule.survival[2] = true;
+        rule.survival[3] = true;
+        rule
+    }
+
+    pub fn born(&self, alive_neighbours: usize) -> bool {
+        self.birth.get(alive_neighbours).copied().unwrap_or(false)
+    }
+
+    pub fn survives(&self, alive_neighbours: usize) -> bool {
+        self.survival
+            .get(alive_neighbours)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Number of intermediate states a cell goes through while dying.
+    pub fn dying_states(&self) -> usize {
+        self.states - 2
+    }
+
+    /// State reached at the given step of the dying sequence, starting at 1
+    /// for an alive cell that failed to survive.
+    pub fn decay(&self, dying_step: usize) -> CellState {
+        if dying_step <= self.dying_states() {
+            CellState::Dying(dying_step)
+        } else {
+            CellState::Dead
+        }
+    }
+
+    /// State of a cell in the next generation given its alive neighbours.
+    pub fn next_state(&self, cell: &CellState, alive_neighbours: usize) -> CellState {
+        match cell {
+            CellState::Dead if self.born(alive_neighbours) => CellState::Alive(0),
+            CellState::Dead => CellState::Dead,
+            CellState::Alive(c) if self.survives(alive_neighbours) => CellState::Alive(c + 1),
+            CellState::Alive(_) => self.decay(1),
+            CellState::Dying(d) => self.decay(d + 1),
+        }
+    }
+
+    /// Offsets of the cells counted as neighbours, including the cell itself
+    /// when the rule counts the centre.
+    pub fn neighbour_offsets(&self) -> Vec<(i32, i32)> {
+        let mut offsets = self.neighbourhood.offsets();
+        if self.include_centre {
+            offsets.push((0, 0));
+        }
+        offsets
+    }
+
+    /// Name of the preset matching this rule, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        RULE_PRESETS
+            .iter()
+            .find(|(_, rulestring)| rulestring.parse::<Rule>().ok().as_ref() == Some(self))
+            .map(|(name, _)| *name)
+    }
+
+    /// Index of the preset matching this rule, if any.
+    pub fn preset_index(&self) -> Option<usize> {
+        RULE_PRESETS
+            .iter()
+            .position(|(_, rulestring)| rulestring.parse::<Rule>().ok().as_ref() == Some(self))
+    }
+
+    pub fn from_preset(index: usize) -> Self {
+        let (_, rulestring) = RULE_PRESETS[index % RULE_PRESETS.len()];
+        rulestring.parse().expect("Rule presets must be valid")
+    }
+
+    /// Parses the B/S notation ("B36/S23") and the older S/B notation ("23/36"),
+    /// optionally followed by a Generations state count ("B2/S/C3", "345/2/4")
+    /// and a neighbourhood suffix, `H` for hexagonal or `V` for von Neumann.
+    fn parse_bs(s: &str) -> anyhow::Result<Self> {
+        let (s, neighbourhood) = match s.chars().last() {
+            Some('H' | 'h') => (&s[..s.len() - 1], Neighbourhood::Hexagonal(1)),
+            Some('V' | 'v') => (&s[..s.len() - 1], Neighbourhood::VonNeumann(1)),
+            _ => (s, Neighbourhood::Moore(1)),
+        };
+
+        let parts = s.split('/').collect::<Vec<&str>>();
+        if parts.len() != 2 && parts.len() != 3 {
+            anyhow::bail!("Invalid rulestring '{}', expected the form B3/S23", s);
+        }
+
+        let states = match parts.get(2) {
+            Some(states) => {
+                let states = states.trim_start_matches(['C', 'c', 'G', 'g']);
+                match states.parse::<usize>() {
+                    Ok(states) if states >= 2 => states,
+                    _ => anyhow::bail!("Invalid state count '{}' in rule", parts[2]),
+                }
+            }
+            None => 2,
+        };
+
+        let (birth, survival) = match (parts[0].chars().next(), parts[1].chars().next()) {
+            (Some('B' | 'b'), Some('S' | 's')) => (&parts[0][1..], &parts[1][1..]),
+            (Some('S' | 's'), Some('B' | 'b')) => (&parts[1][1..], &parts[0][1..]),
+            // Without prefixes the classic notation lists survival first.
+            _ => (parts[1], parts[0]),
+        };
+
+        let max_count = neighbourhood.size();
+        Ok(Self {
+            birth: Self::parse_digits(birth, max_count)?,
+            survival: Self::parse_digits(survival, max_count)?,
+            states,
+            neighbourhood,
+            include_centre: false,
+        })
+    }
+
+    /// Parses the Larger than Life notation, "R5,C0,M1,S34..58,B34..45,NM".
+    ///
+    /// `N` accepts `M` (Moore), `N` (von Neumann), `H` (hexagonal) or `@`
+    /// followed by a custom neighbourhood mask, see [`Neighbourhood::from_mask`].
+    /// Several ranges can be given for `S` and `B`, as in "S2..3,5..6".
+    fn parse_ltl(s: &str) -> anyhow::Result<Self> {
+        let mut radius = 1;
+        let mut states = 2;
+        let mut include_centre = false;
+        let mut neighbourhood = None;
+        let mut birth = Vec::new();
+        let mut survival = Vec::new();
+        let mut last_counts = None;
+
+        for token in s.split(',').map(str::trim) {
+            let mut chars = token.chars();
+            let key = chars.next().map(|c| c.to_ascii_uppercase());
+            let value = chars.as_str();
+            match key {
+                Some('R') => radius = value.parse()?,
+                Some('C') => states = std::cmp::max(value.parse()?, 2),
+                Some('M') => include_centre = value == "1",
+                Some('N') => neighbourhood = Some(value.to_string()),
+                Some('S') => {
+                    survival.extend(Self::parse_range(value)?);
+                    last_counts = Some('S');
+                }
+                Some('B') => {
+                    birth.extend(Self::parse_range(value)?);
+                    last_counts = Some('B');
+                }
+                Some(c) if c.is_ascii_digit() => match last_counts {
+                    Some('S') => survival.extend(Self::parse_range(token)?),
+                    Some('B') => birth.extend(Self::parse_range(token)?),
+                    _ => anyhow::bail!("Neighbour counts '{}' must follow S or B", token),
+                },
+                _ => anyhow::bail!("Invalid field '{}' in rule '{}'", token, s),
+            }
+        }
+
+        if radius == 0 || radius > MAX_RADIUS {
+            anyhow::bail!("Rule radius must be between 1 and {}", MAX_RADIUS);
+        }
+
+        let neighbourhood = match neighbourhood.as_deref() {
+            None => Neighbourhood::Moore(radius),
+            Some(mask) if mask.starts_with('@') => Neighbourhood::from_mask(radius, &mask[1..])?,
+            Some(letter) => match letter.chars().next() {
+                Some(c) if letter.len() == 1 => Neighbourhood::from_letter(c, radius)?,
+                _ => anyhow::bail!("Invalid neighbourhood '{}'", letter),
+            },
+        };
+
+        let max_count = neighbourhood.size() + include_centre as usize;
+        Ok(Self {
+            birth: Self::counts_from_ranges(&birth, max_count)?,
+            survival: Self::counts_from_ranges(&survival, max_count)?,
+            states,
+            neighbourhood,
+            include_centre,
+        })
+    }
+
+    /// Parses "min..max", "min-max" or a single count into inclusive bounds,
+    /// none for an empty string.
+    fn parse_range(range: &str) -> anyhow::Result<Option<(usize, usize)>> {
+        if range.is_empty() {
+            return Ok(None);
+        }
+
+        let (min, max) = match range.split_once("..").or_else(|| range.split_once('-')) {
+            Some((min, max)) => (min.parse::<usize>()?, max.parse::<usize>()?),
+            None => {
+                let count = range.parse::<usize>()?;
+                (count, count)
+            }
+        };
+        if min > max {
+            anyhow::bail!("Neighbour count range '{}' starts above its end", range);
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn parse_digits(digits: &str, max_count: usize) -> anyhow::Result<Vec<bool>> {
+        let counts = digits
+            .chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as usize)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid neighbour count '{}' in rule", c))
+            })
+            .collect::<anyhow::Result<Vec<usize>>>()?;
+        Self::counts_from(&counts, max_count)
+    }
+
+    /// Checks the ranges against the neighbourhood size before expanding them.
+    fn counts_from_ranges(
+        ranges: &[(usize, usize)],
+        max_count: usize,
+    ) -> anyhow::Result<Vec<bool>> {
+        if let Some((_, max)) = ranges.iter().find(|(_, max)| *max > max_count) {
+            anyhow::bail!(
+                "Neighbour count {} is bigger than the neighbourhood size {}",
+                max,
+                max_count
+            );
+        }
+        let counts = ranges.iter().flat_map(|&(min, max)| min..=max).collect::<Vec<_>>();
+        Self::counts_from(&counts, max_count)
+    }
+
+    fn counts_from(counts: &[usize], max_count: usize) -> anyhow::Result<Vec<bool>> {
+        let mut set = vec![false; max_count + 1];
+        for count in counts {
+            match set.get_mut(*count) {
+                Some(flag) => *flag = true,
+                None => anyhow::bail!(
+                    "Neighbour count {} is bigger than the neighbourhood size {}",
+                    count,
+                    max_count
+                ),
+            }
+        }
+        Ok(set)
+    }
+
+    fn format_digits(counts: &[bool]) -> String {
+        counts
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| **set)
+            .map(|(n, _)| n.to_string())
+            .collect()
+    }
+
+    fn format_ranges(counts: &[bool]) -> String {
+        let mut ranges = Vec::new();
+        let mut n = 0;
+        while n < counts.len() {
+            if !counts[n] {
+                n += 1;
+                continue;
+            }
+            let start = n;
+            while n + 1 < counts.len() && counts[n + 1] {
+                n += 1;
+            }
+            ranges.push(if start == n {
+                start.to_string()
+            } else {
+                format!("{}..{}", start, n)
+            });
+            n += 1;
+        }
+        ranges.join(",")
+    }
+
+    /// Whether the rule can be written with the short B/S notation.
+    fn is_bs_notation(&self) -> bool {
+        !self.include_centre
+            && matches!(
+                self.neighbourhood,
+                Neighbourhood::Moore(1)
+                    | Neighbourhood::VonNeumann(1)
+                    | Neighbourhood::Hexagonal(1)
+            )
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    /// Parses either the B/S notation or the Larger than Life notation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with(['R', 'r']) && s.contains(',') {
+            Self::parse_ltl(s)
+        } else {
+            Self::parse_bs(s)
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_bs_notation() {
+            let neighbourhood = match self.neighbourhood.letter() {
+                Some(letter) => letter.to_string(),
+                None => format!("@{}", self.neighbourhood.to_mask()),
+            };
+            return write!(
+                f,
+                "R{},C{},M{},S{},B{},N{}",
+                self.neighbourhood.radius(),
+                if self.states > 2 { self.states } else { 0 },
+                self.include_centre as usize,
+                Self::format_ranges(&self.survival),
+                Self::format_ranges(&self.birth),
+                neighbourhood
+            );
+        }
+
+        write!(
+            f,
+            "B{}/S{}",
+            Self::format_digits(&self.birth),
+            Self::format_digits(&self.survival)
+        )?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        match self.neighbourhood {
+            Neighbourhood::Hexagonal(_) => write!(f, "H"),
+            Neighbourhood::VonNeumann(_) => write!(f, "V"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn larger_than_life_ranges_are_parsed() {
+        let rule = "R5,C0,M1,S34..58,B34..45,NM".parse::<Rule>().unwrap();
+        assert!(rule.survives(34) && rule.survives(58));
+        assert!(!rule.survives(33) && !rule.survives(59));
+        assert!(rule.born(34) && rule.born(45));
+        assert!(!rule.born(46));
+    }
+
+    #[test]
+    fn reversed_ranges_are_rejected() {
+        assert!("R5,C0,M1,S58..34,B34..45,NM".parse::<Rule>().is_err());
+        assert!("R1,C0,M0,S3-2,B3,NM".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn counts_beyond_the_neighbourhood_are_rejected() {
+        assert!("R1,C0,M0,S0..99999999999,B3,NM".parse::<Rule>().is_err());
+        assert!("R1,C0,M0,S2..3,B9,NM".parse::<Rule>().is_err());
+        // The centre cell counts when included.
+        assert!("R1,C0,M1,S2..3,B9,NM".parse::<Rule>().is_ok());
+    }
+
+    #[test]
+    fn custom_neighbourhoods_are_read_back() {
+        let rule = "R1,C0,M0,S2..3,B3,N@a8a".parse::<Rule>().unwrap();
+        assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn empty_custom_neighbourhoods_are_rejected() {
+        assert!("R1,C0,M0,S,B,N@000".parse::<Rule>().is_err());
+        // Only the centre bit set.
+        assert!("R1,C0,M1,S,B,N@080".parse::<Rule>().is_err());
+    }
+}
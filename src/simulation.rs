@@ -0,0 +1,389 @@
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::{
+    active_tiles::ActiveTiles,
+    anchor::Anchor,
+    border_policy::BorderPolicy,
+    cell_state::CellState,
+    double_buffer_grid::DoubleBufferGrid,
+    engine::Engine,
+    hashlife::HashLife,
+    history::{EntryKind, History},
+    packed_grid::PackedGrid,
+    pattern::Pattern,
+    rule::Rule,
+};
+
+/// Memory the history may take unless configured otherwise, in bytes.
+pub const DEFAULT_HISTORY_BUDGET: usize = 64 * 1024 * 1024;
+
+/// A universe of cells evolving under a rule, independent of any interface.
+///
+/// Bounded universes hold the cells from the origin to their size. Unbounded
+/// ones grow in every direction, their size only being the area filled by
+/// random fills and where patterns are centred.
+///
+/// Bounded universes run on a bit-packed grid whenever the rule allows it,
+/// see [`PackedGrid::supports`], and on a dense grid otherwise.
+pub struct Simulation {
+    engine: Engine,
+    width: usize,
+    height: usize,
+    generation: usize,
+    history: History,
+    border_policy: BorderPolicy,
+    track_ages: bool,
+    threads: usize,
+    pub rule: Rule,
+}
+
+impl Simulation {
+    /// Creates an empty universe running Conway's Game of Life, clamped at the edges.
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut simulation = Self {
+            engine: Engine::for_policy(&BorderPolicy::Clamp, width, height),
+            width,
+            height,
+            generation: 0,
+            history: History::new(DEFAULT_HISTORY_BUDGET),
+            border_policy: BorderPolicy::Clamp,
+            track_ages: true,
+            threads: 1,
+            rule: Rule::default(),
+        };
+        simulation.fit_engine();
+        simulation
+    }
+
+    /// Moves bounded universes to the bit-packed grid when the rule allows it,
+    /// or back to the dense grid when it does not.
+    fn fit_engine(&mut self) {
+        let packed = PackedGrid::supports(&self.rule);
+        let mut engine = match &self.engine {
+            Engine::Grid(_) if packed => Engine::Packed(PackedGrid::new(
+                self.width,
+                self.height,
+                self.track_ages,
+            )),
+            Engine::Packed(_) if !packed => Engine::Grid(DoubleBufferGrid::new(self.width, self.height)),
+            _ => return,
+        };
+        for ((x, y), state) in self.engine.cells() {
+            engine.set_cell(x, y, state);
+        }
+        self.engine = engine;
+    }
+
+    /// Whether the age of the cells is kept, for the bit-packed grid where it
+    /// takes a side buffer. Cells are aged 0 otherwise.
+    pub fn tracks_ages(&self) -> bool {
+        self.track_ages
+    }
+
+    pub fn set_track_ages(&mut self, track_ages: bool) {
+        self.track_ages = track_ages;
+        if let Engine::Packed(grid) = &self.engine {
+            if grid.tracks_ages() != track_ages {
+                let mut packed = PackedGrid::new(self.width, self.height, track_ages);
+                for ((x, y), state) in grid.cells() {
+                    packed.set_cell(x as usize, y as usize, state);
+                }
+                self.engine = Engine::Packed(packed);
+            }
+        }
+    }
+
+    /// Threads computing each generation of a bounded universe, in bands of rows.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Sets the amount of threads computing each generation, at least one.
+    /// The cells are the same whatever the amount.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn border_policy(&self) -> &BorderPolicy {
+        &self.border_policy
+    }
+
+    /// Changes what happens at the edges, moving the cells to the engine the
+    /// policy needs. Cells out of the universe size are dropped when going
+    /// from an unbounded universe to a bounded one.
+    pub fn set_border_policy(&mut self, policy: BorderPolicy) {
+        let bounded = policy != BorderPolicy::Unbounded;
+        if bounded != self.engine.is_bounded() {
+            let mut engine = Engine::for_policy(&policy, self.width, self.height);
+            for ((x, y), state) in self.engine.cells() {
+                engine.set_cell(x, y, state);
+            }
+            self.engine = engine;
+        }
+        self.border_policy = policy;
+        self.fit_engine();
+    }
+
+    /// Whether generations are computed with HashLife, see [`Simulation::step_pow2`].
+    pub fn is_hyperspeed(&self) -> bool {
+        matches!(self.engine, Engine::HashLife(_))
+    }
+
+    /// Switches to the HashLife engine or back to the sparse one, making the
+    /// universe unbounded. Fails when the rule is not supported by HashLife,
+    /// see [`HashLife::supports`]. Cells lose their age.
+    pub fn set_hyperspeed(&mut self, hyperspeed: bool) -> anyhow::Result<()> {
+        if hyperspeed == self.is_hyperspeed() {
+            return Ok(());
+        }
+        if hyperspeed && !HashLife::supports(&self.rule) {
+            bail!("Hyperspeed needs a two state rule with the Moore neighbourhood and no B0");
+        }
+
+        let cells = self.engine.alive_cells();
+        self.engine = if hyperspeed {
+            Engine::HashLife(HashLife::from_cells(&cells))
+        } else {
+            let mut engine = Engine::for_policy(&BorderPolicy::Unbounded, self.width, self.height);
+            for (x, y) in cells {
+                engine.set_cell(x, y, CellState::Alive(0));
+            }
+            engine
+        };
+        self.border_policy = BorderPolicy::Unbounded;
+        Ok(())
+    }
+
+    /// Whether the universe only holds the cells from the origin to its size.
+    pub fn is_bounded(&self) -> bool {
+        self.engine.is_bounded()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Changes the universe size, keeping the cells in place around the anchor.
+    /// The history of a bounded universe is emptied as its cell positions no
+    /// longer apply, unbounded universes keep their cells where they are.
+    pub fn resize(&mut self, width: usize, height: usize, anchor: Anchor) {
+        self.width = width;
+        self.height = height;
+        if self.engine.is_bounded() {
+            self.engine.resize(width, height, anchor);
+            self.history.clear();
+        }
+    }
+
+    /// Amount of generations computed since the universe was last filled.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Computes the next generation.
+    pub fn step(&mut self) {
+        self.step_n(1);
+    }
+
+    /// Computes the given amount of generations. HashLife computes them in
+    /// jumps of powers of two that are not recorded, emptying the history.
+    pub fn step_n(&mut self, generations: usize) {
+        self.leave_unsupported_hyperspeed();
+        self.fit_engine();
+        if let Engine::HashLife(hashlife) = &mut self.engine {
+            hashlife.step(&self.rule, generations as u64);
+            self.generation += generations;
+            self.history.clear();
+            return;
+        }
+
+        for _ in 0..generations {
+            self.generation += 1;
+            self.engine
+                .step(&self.rule, &self.border_policy, self.threads);
+            if self.history.is_enabled() {
+                self.history.record_generation(self.engine.changes());
+            }
+        }
+    }
+
+    /// Computes `2^exponent` generations in a single jump with HashLife. Fails
+    /// out of hyperspeed, where the jump would take as many single steps.
+    pub fn step_pow2(&mut self, exponent: u32) -> anyhow::Result<()> {
+        self.leave_unsupported_hyperspeed();
+        let Engine::HashLife(hashlife) = &mut self.engine else {
+            bail!("Jumps of 2^{} generations need hyperspeed", exponent);
+        };
+
+        hashlife.step_pow2(&self.rule, exponent as u8);
+        self.generation += 1 << exponent;
+        self.history.clear();
+        Ok(())
+    }
+
+    /// Goes back to the sparse engine when the rule changed to one HashLife
+    /// does not support.
+    fn leave_unsupported_hyperspeed(&mut self) {
+        if self.is_hyperspeed() && !HashLife::supports(&self.rule) {
+            let _ = self.set_hyperspeed(false);
+        }
+    }
+
+    /// State of the cell at the given point of the current generation, `None`
+    /// outside of a bounded universe.
+    pub fn get_cell(&self, x: i64, y: i64) -> Option<CellState> {
+        self.engine.get_cell(x, y)
+    }
+
+    /// Sets a cell, recording it in the history as part of the current edit.
+    pub fn set_cell(&mut self, x: i64, y: i64, state: CellState) {
+        if let Some(before) = self.get_cell(x, y) {
+            self.history.record_edit((x, y), before, state.clone());
+            self.engine.set_cell(x, y, state);
+        }
+    }
+
+    /// Closes the current edit, so it is undone at once.
+    pub fn commit_edit(&mut self) {
+        self.history.commit_edit();
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Reverts the last generation or edit, returning whether there was one.
+    pub fn undo(&mut self) -> bool {
+        let Some(entry) = self.history.take_undo() else {
+            return false;
+        };
+
+        // Ages go back first so the cells restored below keep their own age.
+        if entry.kind == EntryKind::Generation {
+            self.engine.age_cells(-1);
+            self.generation -= 1;
+        }
+        for change in entry.changes.iter() {
+            let (x, y) = change.cell;
+            self.engine.set_cell(x, y, change.before.clone());
+        }
+
+        self.history.push_redo(entry);
+        true
+    }
+
+    /// Applies again the last undone generation or edit, returning whether there was one.
+    pub fn redo(&mut self) -> bool {
+        let Some(entry) = self.history.take_redo() else {
+            return false;
+        };
+
+        if entry.kind == EntryKind::Generation {
+            self.engine.age_cells(1);
+            self.generation += 1;
+        }
+        for change in entry.changes.iter() {
+            let (x, y) = change.cell;
+            self.engine.set_cell(x, y, change.after.clone());
+        }
+
+        self.history.push_undo(entry);
+        true
+    }
+
+    /// Amount of alive cells.
+    pub fn population(&self) -> usize {
+        self.engine.population()
+    }
+
+    /// Smallest rectangle containing every alive cell, as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(i64, i64, usize, usize)> {
+        self.engine.bounding_box()
+    }
+
+    /// Tiles the next step computes in a bounded universe, the others being stable.
+    pub fn active_tiles(&self) -> Option<&ActiveTiles> {
+        self.engine.active_tiles()
+    }
+
+    /// Positions of the alive cells inside a rectangle, faster than going
+    /// through every cell of a large sparse area.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        self.engine.alive_cells_in(x, y, width, height)
+    }
+
+    /// Kills every cell, starting a new run with an empty history.
+    pub fn clear(&mut self) {
+        self.engine.clear();
+        self.generation = 0;
+        self.history.clear();
+    }
+
+    /// Fills the universe size with random cells, see [`Engine::randomize`],
+    /// starting a new run with an empty history.
+    pub fn randomize(&mut self, seed: u64, density: f64) {
+        self.engine
+            .randomize(seed, density, self.width, self.height);
+        self.generation = 0;
+        self.history.clear();
+    }
+
+    /// Replaces the universe content with a pattern, centred unless an offset is
+    /// given, and switches to the pattern rule if it has one. The history is emptied.
+    pub fn load_pattern(&mut self, pattern: &Pattern, offset: Option<(usize, usize)>) {
+        let (x, y) = offset.unwrap_or((
+            self.width.saturating_sub(pattern.width) / 2,
+            self.height.saturating_sub(pattern.height) / 2,
+        ));
+
+        self.engine.clear();
+        for (cell_x, cell_y) in pattern.cells.iter() {
+            self.engine.set_cell(
+                (x + cell_x) as i64,
+                (y + cell_y) as i64,
+                CellState::Alive(0),
+            );
+        }
+        self.generation = 0;
+        self.history.clear();
+
+        if let Some(rule) = &pattern.rule {
+            self.rule = rule.clone();
+        }
+    }
+
+    /// Pattern of the current generation, cropped to the alive cells and
+    /// recording the rule, border policy and generation.
+    pub fn to_pattern(&self) -> Pattern {
+        let mut pattern = Pattern::from_cells(&self.engine.alive_cells());
+        pattern.rule = Some(self.rule.clone());
+        pattern.comments = vec![
+            format!("Border policy: {:?}", self.border_policy),
+            format!("Generation: {}", self.generation),
+        ];
+        pattern
+    }
+
+    /// Loads a pattern file, see [`Simulation::load_pattern`].
+    pub fn load(&mut self, path: &Path, offset: Option<(usize, usize)>) -> anyhow::Result<()> {
+        let pattern = Pattern::load(path)?;
+        self.load_pattern(&pattern, offset);
+        Ok(())
+    }
+
+    /// Saves the current generation to a pattern file, the format being
+    /// picked from the extension.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        self.to_pattern().save(path)
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{cell_state::CellState, history::Change, rule::Rule};
+
+/// Unbounded universe storing only the cells that are not dead, so it grows
+/// as the patterns expand.
+///
+/// Only the cells around the alive ones are computed, so rules where cells are
+/// born without any alive neighbour (B0) do not fill the empty space.
+#[derive(Debug, Default)]
+pub struct SparseGrid {
+    cells: HashMap<(i64, i64), CellState>,
+    /// Cells of the previous generation, to tell what the last step changed.
+    previous: HashMap<(i64, i64), CellState>,
+}
+
+impl SparseGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fills the given rectangle from the origin with random cells, picking the
+    /// same cells as a dense grid of that size for the same seed.
+    pub fn randomize(&mut self, seed: u64, density: f64, width: usize, height: usize) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        self.clear();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                if rng.gen_bool(density) {
+                    self.cells.insert((x, y), CellState::Alive(0));
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.previous.clear();
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> &CellState {
+        self.cells.get(&(x, y)).unwrap_or(&CellState::Dead)
+    }
+
+    pub fn set_cell(&mut self, x: i64, y: i64, state: CellState) {
+        match state {
+            CellState::Dead => self.cells.remove(&(x, y)),
+            state => self.cells.insert((x, y), state),
+        };
+    }
+
+    /// Changes the age of every alive cell.
+    pub fn age_cells(&mut self, delta: isize) {
+        for cell in self.cells.values_mut() {
+            if let CellState::Alive(age) = cell {
+                *age = age.saturating_add_signed(delta);
+            }
+        }
+    }
+
+    /// Amount of alive cells.
+    pub fn population(&self) -> usize {
+        self.cells
+            .values()
+            .filter(|cell| matches!(cell, CellState::Alive(_)))
+            .count()
+    }
+
+    /// Cells that are not dead, with their state.
+    pub fn cells(&self) -> Vec<((i64, i64), CellState)> {
+        self.cells
+            .iter()
+            .map(|(position, cell)| (*position, cell.clone()))
+            .collect()
+    }
+
+    /// Positions of the alive cells.
+    pub fn alive_cells(&self) -> Vec<(i64, i64)> {
+        self.cells
+            .iter()
+            .filter(|(_, cell)| matches!(cell, CellState::Alive(_)))
+            .map(|(position, _)| *position)
+            .collect()
+    }
+
+    /// Positions of the alive cells inside a rectangle.
+    pub fn alive_cells_in(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<(i64, i64)> {
+        let x_range = x..x + width as i64;
+        let y_range = y..y + height as i64;
+        self.alive_cells()
+            .into_iter()
+            .filter(|(cell_x, cell_y)| x_range.contains(cell_x) && y_range.contains(cell_y))
+            .collect()
+    }
+
+    /// Smallest rectangle containing every alive cell, as `(x, y, width, height)`.
+    pub fn bounding_box(&self) -> Option<(i64, i64, usize, usize)> {
+        let alive = self.alive_cells();
+        let min_x = alive.iter().map(|(x, _)| *x).min()?;
+        let min_y = alive.iter().map(|(_, y)| *y).min()?;
+        let max_x = alive.iter().map(|(x, _)| *x).max()?;
+        let max_y = alive.iter().map(|(_, y)| *y).max()?;
+        Some((
+            min_x,
+            min_y,
+            (max_x - min_x + 1) as usize,
+            (max_y - min_y + 1) as usize,
+        ))
+    }
+
+    /// Computes the next generation.
+    pub fn step(&mut self, rule: &Rule) {
+        let offsets = rule.neighbour_offsets();
+
+        // Every alive cell adds itself to the count of the cells it is a neighbour of.
+        let mut counts = HashMap::<(i64, i64), usize>::new();
+        for ((x, y), cell) in self.cells.iter() {
+            if let CellState::Alive(_) = cell {
+                for (dx, dy) in offsets.iter() {
+                    *counts.entry((x - *dx as i64, y - *dy as i64)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut next = HashMap::with_capacity(self.cells.len());
+        for (position, cell) in self.cells.iter() {
+            let alive_neighbours = counts.get(position).copied().unwrap_or(0);
+            match rule.next_state(cell, alive_neighbours) {
+                CellState::Dead => {}
+                state => {
+                    next.insert(*position, state);
+                }
+            }
+        }
+        for (position, alive_neighbours) in counts {
+            if !self.cells.contains_key(&position) && rule.born(alive_neighbours) {
+                next.insert(position, CellState::Alive(0));
+            }
+        }
+
+        self.previous = std::mem::replace(&mut self.cells, next);
+    }
+
+    /// Cells changed by the last step, see [`Change::generation`].
+    pub fn changes(&self) -> Vec<Change> {
+        let mut changes = self
+            .previous
+            .iter()
+            .filter_map(|(position, before)| {
+                Change::generation(*position, before, self.get_cell(position.0, position.1))
+            })
+            .collect::<Vec<Change>>();
+        changes.extend(
+            self.cells
+                .iter()
+                .filter(|(position, _)| !self.previous.contains_key(position))
+                .filter_map(|(position, after)| {
+                    Change::generation(*position, &CellState::Dead, after)
+                }),
+        );
+        changes
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+/// Intervals between updates the speed keys go through, from slowest to fastest.
+/// Zero runs an update on every frame.
+const INTERVALS_MS: [u64; 13] = [4000, 2000, 1000, 500, 250, 150, 100, 75, 50, 25, 10, 5, 0];
+
+/// Biggest amount of generations computed on a single update.
+const MAX_GENERATIONS_PER_UPDATE: usize = 1024;
+
+/// How often the measured generations per second is refreshed.
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Pace of the simulation, and measure of the pace actually reached.
+#[derive(Debug)]
+pub struct Speed {
+    interval: Duration,
+    /// Generations computed on each update, to go past one generation per frame.
+    pub generations_per_update: usize,
+    last_update: Instant,
+    window_start: Instant,
+    window_generations: usize,
+    measured_rate: f64,
+}
+
+impl Speed {
+    pub fn new(interval_ms: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            interval: Duration::from_millis(interval_ms),
+            generations_per_update: 1,
+            last_update: now,
+            window_start: now,
+            window_generations: 0,
+            measured_rate: 0.0,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Shortens the interval to the next step of the speed ladder.
+    pub fn faster(&mut self) {
+        let current = self.interval.as_millis() as u64;
+        if let Some(ms) = INTERVALS_MS.iter().find(|ms| **ms < current) {
+            self.interval = Duration::from_millis(*ms);
+        }
+    }
+
+    /// Lengthens the interval to the previous step of the speed ladder.
+    pub fn slower(&mut self) {
+        let current = self.interval.as_millis() as u64;
+        if let Some(ms) = INTERVALS_MS.iter().rev().find(|ms| **ms > current) {
+            self.interval = Duration::from_millis(*ms);
+        }
+    }
+
+    pub fn more_per_update(&mut self) {
+        self.generations_per_update =
+            std::cmp::min(self.generations_per_update * 2, MAX_GENERATIONS_PER_UPDATE);
+    }
+
+    pub fn fewer_per_update(&mut self) {
+        self.generations_per_update = std::cmp::max(self.generations_per_update / 2, 1);
+    }
+
+    /// Whether the interval since the last update has passed, starting a new
+    /// interval if so.
+    pub fn is_due(&mut self) -> bool {
+        let now = Instant::now();
+
+        let window = now.duration_since(self.window_start);
+        if window >= MEASURE_WINDOW {
+            self.measured_rate = self.window_generations as f64 / window.as_secs_f64();
+            self.window_start = now;
+            self.window_generations = 0;
+        }
+
+        if now.duration_since(self.last_update) < self.interval {
+            return false;
+        }
+        self.last_update = now;
+        true
+    }
+
+    /// Counts generations computed towards the measured rate.
+    pub fn record(&mut self, generations: usize) {
+        self.window_generations += generations;
+    }
+
+    /// Generations per second aimed at when computing the given amount on each
+    /// update, `None` when running as fast as possible.
+    pub fn target_rate(&self, generations_per_update: usize) -> Option<f64> {
+        if self.interval.is_zero() {
+            return None;
+        }
+        Some(generations_per_update as f64 / self.interval.as_secs_f64())
+    }
+
+    /// Generations per second measured over the last second.
+    pub fn measured_rate(&self) -> f64 {
+        self.measured_rate
+    }
+}
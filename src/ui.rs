@@ -6,42 +6,225 @@ use ratatui::{
     Frame,
 };
 
-use crate::{app::App, cell_state::CellState};
+use std::collections::HashSet;
+
+use conways_tui::cell_state::CellState;
+
+use crate::{app::App, edit_mode::EditMode, zoom::Zoom};
 
 const MAX_LIFE_CYCLES: usize = 10;
 
-/// Renders the user interface widgets.
-pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
-    let mut cells = Vec::with_capacity((app.grid_width * app.grid_height) as usize);
-    let read = app.grids.get_render_grid();
-    for y in 0..app.grid_height {
-        let mut row = Vec::new();
-
-        for x in 0..app.grid_width {
-            let index = y * app.grid_width + x;
-            let cell = match &read[index as usize] {
-                // CellState::Alive(_) => Cell::from("██").bg(Color::Black).fg(Color::White),
-                CellState::Alive(c) => {
-                    let c = std::cmp::min(*c, MAX_LIFE_CYCLES);
-                    let dc = c as f64 / MAX_LIFE_CYCLES as f64;
-                    // let dc = 1.0 - dc;
-                    let col = (dc * 255.0) as u8;
-                    let col = Color::Rgb(255 - col, col, col);
-
-                    // Cell::from("  ").bg(Color::White).fg(Color::Black)
-                    Cell::from("  ").bg(col).fg(Color::Black)
+/// Colour of the edit cursor and of the pending line or rectangle.
+const CURSOR_COLOR: Color = Color::Yellow;
+
+/// Background of the tiles the next step computes, when shown.
+const ACTIVE_TILE_COLOR: Color = Color::Rgb(20, 30, 60);
+
+/// Colours of the first and last dying states of Generations rules.
+const DYING_COLOR_START: (u8, u8, u8) = (255, 170, 0);
+const DYING_COLOR_END: (u8, u8, u8) = (50, 20, 90);
+
+/// Interpolates the dying colour ramp, `t` going from 0.0 (just died) to 1.0.
+fn dying_color(t: f64) -> Color {
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t) as u8;
+    Color::Rgb(
+        lerp(DYING_COLOR_START.0, DYING_COLOR_END.0),
+        lerp(DYING_COLOR_START.1, DYING_COLOR_END.1),
+        lerp(DYING_COLOR_START.2, DYING_COLOR_END.2),
+    )
+}
+
+/// Shades of the density zoom, from empty to full blocks.
+const DENSITY_SHADES: [&str; 5] = [" ", "░", "▒", "▓", "█"];
+
+/// Colour of a cell, `None` when it is dead.
+fn cell_color(cell: &CellState, dying_states: usize) -> Option<Color> {
+    match cell {
+        CellState::Alive(c) => {
+            let c = std::cmp::min(*c, MAX_LIFE_CYCLES);
+            let dc = c as f64 / MAX_LIFE_CYCLES as f64;
+            let col = (dc * 255.0) as u8;
+            Some(Color::Rgb(255 - col, col, col))
+        }
+        CellState::Dying(d) => {
+            let dying_states = std::cmp::max(dying_states, 1);
+            let dc = std::cmp::min(*d, dying_states) as f64 / dying_states as f64;
+            Some(dying_color(dc))
+        }
+        CellState::Dead => None,
+    }
+}
+
+/// Lines of characters drawing the part of the universe in view at the current zoom.
+fn grid_lines(app: &App) -> Vec<Line<'static>> {
+    let simulation = &app.simulation;
+    let dying_states = simulation.rule.dying_states();
+    let zoom = app.layout.zoom;
+    let (cells_x, cells_y) = zoom.cells_per_char();
+    let (view_x, view_y) = app.viewport;
+    let (view_width, view_height) = app.view_size();
+
+    let cursor = match app.edit_mode {
+        EditMode::Off => None,
+        _ => Some(app.cursor),
+    };
+    let preview = app.edit_preview().into_iter().collect::<HashSet<_>>();
+    let highlighted = |x: i64, y: i64| cursor == Some((x, y)) || preview.contains(&(x, y));
+    // Colour a cell is drawn with when zoomed out, `None` when it is not drawn.
+    let color_at = |x: i64, y: i64| match highlighted(x, y) {
+        true => Some(CURSOR_COLOR),
+        false => simulation
+            .get_cell(x, y)
+            .and_then(|cell| cell_color(&cell, dying_states)),
+    };
+
+    // Whether a character shows a cell of a tile the next step computes.
+    let active_tiles = simulation.active_tiles().filter(|_| app.show_active_tiles);
+    let active_at = |x: i64, y: i64| match active_tiles {
+        Some(tiles) if x >= 0 && y >= 0 => {
+            let (x, y) = (x as usize, y as usize);
+            x < simulation.width() && y < simulation.height() && tiles.is_active(x, y)
+        }
+        _ => false,
+    };
+
+    let chars_x = view_width.div_ceil(cells_x);
+    let chars_y = view_height.div_ceil(cells_y);
+    let densities = match zoom {
+        Zoom::Density(_) => block_densities(app, chars_x, chars_y),
+        _ => Vec::new(),
+    };
+
+    let mut lines = Vec::with_capacity(chars_y);
+    for char_y in 0..chars_y {
+        let y = view_y + (char_y * cells_y) as i64;
+        let mut spans = Vec::with_capacity(chars_x);
+
+        for char_x in 0..chars_x {
+            let x = view_x + (char_x * cells_x) as i64;
+            let span = match zoom {
+                Zoom::Wide | Zoom::Narrow => {
+                    let background = simulation
+                        .get_cell(x, y)
+                        .and_then(|cell| cell_color(&cell, dying_states))
+                        .unwrap_or(Color::Reset);
+                    let (cursor_symbol, preview_symbol, blank) = match zoom {
+                        Zoom::Wide => ("[]", "::", "  "),
+                        _ => ("+", ":", " "),
+                    };
+
+                    let span = if cursor == Some((x, y)) {
+                        Span::raw(cursor_symbol).fg(CURSOR_COLOR).bold()
+                    } else if preview.contains(&(x, y)) {
+                        Span::raw(preview_symbol).fg(CURSOR_COLOR)
+                    } else {
+                        Span::raw(blank)
+                    };
+                    span.bg(background)
                 }
-                // CellState::Dead => Cell::from(" ").bg(Color::Black).fg(Color::White),
-                CellState::Dead => Cell::from(" ").bg(Color::Reset).fg(Color::White),
-            };
+                Zoom::HalfBlock => match (color_at(x, y), color_at(x, y + 1)) {
+                    (None, None) => Span::raw(" "),
+                    (Some(top), None) => Span::raw("▀").fg(top),
+                    (None, Some(bottom)) => Span::raw("▄").fg(bottom),
+                    (Some(top), Some(bottom)) if top == bottom => Span::raw("█").fg(top),
+                    (Some(top), Some(bottom)) => Span::raw("▀").fg(top).bg(bottom),
+                },
+                Zoom::Braille => {
+                    // Dot bits of the braille patterns, by column then row.
+                    const DOTS: [[u32; 4]; 2] =
+                        [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+                    let mut bits = 0;
+                    let mut colors = Vec::new();
+                    for (dx, column) in DOTS.iter().enumerate() {
+                        for (dy, bit) in column.iter().enumerate() {
+                            if let Some(color) = color_at(x + dx as i64, y + dy as i64) {
+                                bits |= bit;
+                                colors.push(color);
+                            }
+                        }
+                    }
 
-            row.push(cell);
+                    let symbol = char::from_u32(0x2800 + bits).unwrap_or(' ').to_string();
+                    match colors.iter().find(|color| **color == CURSOR_COLOR).or(colors.first()) {
+                        Some(color) => Span::raw(symbol).fg(*color),
+                        None => Span::raw(symbol),
+                    }
+                }
+                Zoom::Density(_) => {
+                    let ratio = densities[char_y * chars_x + char_x];
+                    let shade = (ratio * (DENSITY_SHADES.len() - 1) as f64).ceil() as usize;
+                    let span = Span::raw(DENSITY_SHADES[shade.min(DENSITY_SHADES.len() - 1)]);
+                    let block_highlighted = cursor
+                        .iter()
+                        .chain(preview.iter())
+                        .any(|(cell_x, cell_y)| {
+                            (x..x + cells_x as i64).contains(cell_x)
+                                && (y..y + cells_y as i64).contains(cell_y)
+                        });
+                    match block_highlighted {
+                        true => span.fg(CURSOR_COLOR),
+                        false => span,
+                    }
+                }
+            };
+            // Tinted through the characters without a background of their own.
+            let span = match span.style.bg {
+                None | Some(Color::Reset) if active_at(x, y) => span.bg(ACTIVE_TILE_COLOR),
+                _ => span,
+            };
+            spans.push(span);
         }
 
-        let row = Row::new(row);
-        cells.push(row);
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Ratio of alive cells of each block of cells shown by a character of the
+/// density zoom, row by row. Only the cells inside a bounded universe count.
+fn block_densities(app: &App, chars_x: usize, chars_y: usize) -> Vec<f64> {
+    let simulation = &app.simulation;
+    let (cells_x, cells_y) = app.layout.zoom.cells_per_char();
+    let (view_x, view_y) = app.viewport;
+
+    let mut alive = vec![0; chars_x * chars_y];
+    let cells = simulation.alive_cells_in(view_x, view_y, chars_x * cells_x, chars_y * cells_y);
+    for (x, y) in cells {
+        let char_x = (x - view_x) as usize / cells_x;
+        let char_y = (y - view_y) as usize / cells_y;
+        alive[char_y * chars_x + char_x] += 1;
     }
 
+    // Length of the part of a block range inside the universe.
+    let inside = |start: i64, length: usize, size: usize| match simulation.is_bounded() {
+        true => (start + length as i64).min(size as i64) - start.max(0),
+        false => length as i64,
+    };
+    alive
+        .iter()
+        .enumerate()
+        .map(|(index, alive)| {
+            let x = view_x + ((index % chars_x) * cells_x) as i64;
+            let y = view_y + ((index / chars_x) * cells_y) as i64;
+            let total =
+                inside(x, cells_x, simulation.width()) * inside(y, cells_y, simulation.height());
+            *alive as f64 / total.max(1) as f64
+        })
+        .collect()
+}
+
+/// Renders the user interface widgets.
+pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+    // Only the part of the grid in the viewport is drawn.
+    let simulation = &app.simulation;
+    let (view_x, view_y) = app.viewport;
+    let (visible_width, visible_height) = app.view_size();
+    let cursor = match app.edit_mode {
+        EditMode::Off => None,
+        _ => Some(app.cursor),
+    };
+
     let block = Block::new()
         .title("Game of life")
         .borders(Borders::ALL)
@@ -49,54 +232,214 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
 
     frame.render_widget(block, app.layout.main_layout);
 
-    let table = Table::new(cells)
-        .block(Block::default().borders(Borders::ALL))
-        .widths(&app.layout.grid_constraints)
-        .column_spacing(0);
+    // Position indicator, the range of cells in view out of the universe size.
+    let last_x = view_x + visible_width as i64 - 1;
+    let last_y = view_y + visible_height as i64 - 1;
+    let position = match simulation.is_bounded() {
+        true => format!(
+            "x {}-{} of {}, y {}-{} of {}",
+            view_x,
+            last_x,
+            simulation.width(),
+            view_y,
+            last_y,
+            simulation.height()
+        ),
+        false => format!("x {} to {}, y {} to {}", view_x, last_x, view_y, last_y),
+    };
+    let grid = Paragraph::new(grid_lines(app))
+        .block(Block::default().title(position).borders(Borders::ALL));
 
-    frame.render_widget(table, app.layout.grid_panel);
+    frame.render_widget(grid, app.layout.grid_panel);
 
     let block = Block::new()
         .title("Config")
-        .padding(Padding::new(1, 0, 1, 0))
+        .padding(Padding::new(1, 0, 0, 0))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
 
+    let history = simulation.history();
     let text = Text::from(vec![
         Line::from(vec![
-            Span::raw("Cycle count: "),
-            Span::raw(app.cycle_count.to_string()),
+            Span::raw("Generation: "),
+            Span::raw(format!("{}, {:?}", simulation.generation(), app.player_state)),
+        ]),
+        Line::from(vec![
+            Span::raw("Step: "),
+            Span::raw(app.step_count.unwrap_or(1).to_string()),
+        ]),
+        Line::from(vec![
+            Span::raw("Speed: "),
+            Span::raw(match simulation.is_hyperspeed() {
+                true => format!(
+                    "{}ms x2^{} hyperspeed",
+                    app.speed.interval().as_millis(),
+                    app.step_exponent
+                ),
+                false => format!(
+                    "{}ms x{}",
+                    app.speed.interval().as_millis(),
+                    app.generations_per_update()
+                ),
+            }),
+        ]),
+        Line::from(vec![
+            Span::raw("Rate: "),
+            Span::raw(match app.speed.target_rate(app.generations_per_update()) {
+                Some(rate) => format!("{:.1} of {:.1} gen/s", app.speed.measured_rate(), rate),
+                None => format!("{:.1} gen/s, max", app.speed.measured_rate()),
+            }),
+        ]),
+        Line::from(vec![
+            Span::raw("Rewind: "),
+            Span::raw(format!(
+                "{} gens, {:.1}/{} MB",
+                history.rewind_generations(),
+                history.memory_used() as f64 / (1024.0 * 1024.0),
+                history.memory_budget() / (1024 * 1024)
+            )),
+        ]),
+        Line::from(vec![
+            Span::raw("Active tiles: "),
+            Span::raw(match simulation.active_tiles() {
+                Some(tiles) => format!(
+                    "{} ({}x{}){}",
+                    tiles.count(),
+                    tiles.tile_width(),
+                    tiles.tile_height(),
+                    if app.show_active_tiles { " shown" } else { "" }
+                ),
+                None => "-".to_string(),
+            }),
+        ]),
+        Line::from(vec![
+            Span::raw("Grid: "),
+            Span::raw(format!(
+                "{}x{}, {:?}",
+                simulation.width(),
+                simulation.height(),
+                simulation.border_policy()
+            )),
+        ]),
+        Line::from(vec![
+            Span::raw("Zoom: "),
+            Span::raw(app.layout.zoom.to_string()),
+            Span::raw(if app.follow_population { ", follow" } else { "" }),
         ]),
         Line::from(vec![
-            Span::raw("Grid size: "),
-            Span::raw(format!("{}x{}", app.layout.width(), app.layout.height())),
+            Span::raw("Seed: "),
+            Span::raw(format!("{} at {:.0}%", app.seed, app.density * 100.0)),
         ]),
         Line::from(vec![
-            Span::raw("Player state: "),
-            Span::raw(format!("{:?}", app.player_state)),
+            Span::raw("Edit: "),
+            Span::raw(match (app.edit_mode, cursor) {
+                (EditMode::Off, _) | (_, None) => "Off".to_string(),
+                (EditMode::Cursor, Some((x, y))) => format!("Cursor at {}, {}", x, y),
+                (EditMode::Line { .. }, Some((x, y))) => format!("Line to {}, {}", x, y),
+                (EditMode::Rectangle { .. }, Some((x, y))) => format!("Rectangle to {}, {}", x, y),
+            }),
         ]),
+        // Last, as long rulestrings wrap.
         Line::from(vec![
-            Span::raw("Border policy: "),
-            Span::raw(format!("{:?}", app.border_policy)),
+            Span::raw("Rule: "),
+            Span::raw(match simulation.rule.preset_name() {
+                Some(name) => format!("{} ({})", simulation.rule, name),
+                None => simulation.rule.to_string(),
+            }),
         ]),
     ]);
-    let text = Paragraph::new(text).block(block);
+    let text = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
 
     frame.render_widget(text, app.layout.config_panel);
 
     let block = Block::new()
         .title("Console")
+        .padding(Padding::new(1, 0, 0, 0))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
 
-    frame.render_widget(block, app.layout.console_panel);
+    let visible_lines = app.layout.console_panel.height.saturating_sub(2) as usize;
+    let messages = app
+        .console
+        .iter()
+        .skip(app.console.len().saturating_sub(visible_lines))
+        .map(|message| Line::from(message.as_str()))
+        .collect::<Vec<Line>>();
+    let messages = Paragraph::new(messages)
+        .block(block)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(messages, app.layout.console_panel);
 
     let block = Block::new()
         .title("Cheatsheat")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
 
-    let shortcuts = Text::from("Q: quit  R: reset  P: play/pause  B: switch border policy");
-    let shortcuts = Paragraph::new(shortcuts).block(block);
+    let shortcuts: &[&str] = match app.edit_mode {
+        EditMode::Off => &[
+            "q/Esc: quit  p: play/pause  [N].: step  r/R: new/same seed  d/D: density",
+            "Arrows/hjkl/middle drag: pan  z/Z/wheel: zoom  c/f: centre/follow  t: tiles",
+            "b: border  H: hyperspeed  n/N: next/prev rule  +/-: speed  </>: gen/update",
+            "u/U: undo/redo  [/]: rewind/forward  s: save  e: edit  Mouse: draw/erase",
+        ],
+        EditMode::Cursor => &[
+            "Arrows/hjkl: move (shift+arrows: fast)  Space: toggle",
+            "L: line  F: rectangle  e/Esc: leave edit",
+        ],
+        _ => &[
+            "Arrows/hjkl: move (shift+arrows: fast)",
+            "Enter/Space: draw  Backspace: erase  Esc: cancel",
+        ],
+    };
+    let shortcuts = shortcuts.iter().map(|line| Line::from(*line)).collect::<Vec<Line>>();
+    let shortcuts = Paragraph::new(shortcuts)
+        .block(block)
+        .wrap(Wrap { trim: true });
     frame.render_widget(shortcuts, app.layout.bottom_panel);
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    /// Rows of the terminal after rendering the app at the given size.
+    fn rendered_rows(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| render(app, frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn config_lines_fit_a_small_terminal() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((64, 64)));
+        app.simulation.set_hyperspeed(true).unwrap();
+        app.edit_mode = EditMode::Cursor;
+        app.cursor = (12, 34);
+
+        let rows = rendered_rows(&mut app, 80, 24);
+        for readout in [
+            "Rewind: 0 gens",
+            "Speed: 75ms x2^4 hyperspeed",
+            "Active tiles: -",
+            "Edit: Cursor at 12, 34",
+            "Rule: B3/S23 (Conway's Life)",
+        ] {
+            assert!(rows.iter().any(|row| row.contains(readout)), "{}", readout);
+        }
+    }
+
+    #[test]
+    fn every_binding_fits_a_small_terminal() {
+        let mut app = App::new(Rect::new(0, 0, 80, 24), Some((64, 64)));
+        let rows = rendered_rows(&mut app, 80, 24);
+        for binding in ["q/Esc: quit", "t: tiles", "n/N: next/prev rule", "Mouse: draw/erase"] {
+            assert!(rows.iter().any(|row| row.contains(binding)), "{}", binding);
+        }
+    }
+}
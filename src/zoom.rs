@@ -0,0 +1,70 @@
+/// Largest side of the blocks of cells aggregated into one character.
+const MAX_DENSITY_BLOCK: usize = 64;
+
+/// How many cells each character of the grid panel shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Zoom {
+    /// One cell per two columns.
+    #[default]
+    Wide,
+    /// One cell per column.
+    Narrow,
+    /// Two vertical cells per character, drawn with half blocks.
+    HalfBlock,
+    /// Two by four cells per character, drawn with braille dots.
+    Braille,
+    /// Blocks of `n` by `2n` cells per character, shaded by their density.
+    Density(usize),
+}
+
+impl Zoom {
+    /// Terminal columns each character takes.
+    pub fn columns_per_char(&self) -> usize {
+        match self {
+            Self::Wide => 2,
+            _ => 1,
+        }
+    }
+
+    /// Cells each character shows, horizontally and vertically.
+    pub fn cells_per_char(&self) -> (usize, usize) {
+        match self {
+            Self::Wide | Self::Narrow => (1, 1),
+            Self::HalfBlock => (1, 2),
+            Self::Braille => (2, 4),
+            Self::Density(n) => (*n, 2 * n),
+        }
+    }
+
+    /// Shows fewer cells per character, down to [`Zoom::Wide`].
+    pub fn zoom_in(&mut self) {
+        *self = match self {
+            Self::Wide | Self::Narrow => Self::Wide,
+            Self::HalfBlock => Self::Narrow,
+            Self::Braille => Self::HalfBlock,
+            Self::Density(n) if *n <= 4 => Self::Braille,
+            Self::Density(n) => Self::Density(*n / 2),
+        };
+    }
+
+    /// Shows more cells per character, up to blocks of [`MAX_DENSITY_BLOCK`] cells.
+    pub fn zoom_out(&mut self) {
+        *self = match self {
+            Self::Wide => Self::Narrow,
+            Self::Narrow => Self::HalfBlock,
+            Self::HalfBlock => Self::Braille,
+            Self::Braille => Self::Density(4),
+            Self::Density(n) => Self::Density((*n * 2).min(MAX_DENSITY_BLOCK)),
+        };
+    }
+}
+
+impl std::fmt::Display for Zoom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.cells_per_char();
+        match self {
+            Self::Density(_) => write!(f, "Density {}x{}", width, height),
+            _ => write!(f, "{:?} {}x{}", self, width, height),
+        }
+    }
+}
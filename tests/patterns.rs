@@ -0,0 +1,254 @@
+//! Known patterns run on every engine, checked against their well known evolution.
+
+use conways_tui::{
+    border_policy::BorderPolicy, cell_state::CellState, double_buffer_grid::DoubleBufferGrid,
+    engine::Engine, hashlife::HashLife, packed_grid::PackedGrid, rule::Rule,
+    sparse_grid::SparseGrid, Simulation,
+};
+
+const BLINKER: [(i64, i64); 3] = [(0, 1), (1, 1), (2, 1)];
+const GLIDER: [(i64, i64); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+const R_PENTOMINO: [(i64, i64); 5] = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)];
+
+const BLOCK: [(i64, i64); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+const BEEHIVE: [(i64, i64); 6] = [(1, 0), (2, 0), (0, 1), (3, 1), (1, 2), (2, 2)];
+const LOAF: [(i64, i64); 7] = [(1, 0), (2, 0), (0, 1), (3, 1), (1, 2), (3, 2), (2, 3)];
+const BOAT: [(i64, i64); 5] = [(0, 0), (1, 0), (0, 1), (2, 1), (1, 2)];
+const TUB: [(i64, i64); 4] = [(1, 0), (0, 1), (2, 1), (1, 2)];
+
+/// Every engine, the bounded ones being of the given size.
+fn engines(width: usize, height: usize) -> Vec<(&'static str, Engine)> {
+    vec![
+        ("grid", Engine::Grid(DoubleBufferGrid::new(width, height))),
+        (
+            "packed",
+            Engine::Packed(PackedGrid::new(width, height, true)),
+        ),
+        ("sparse", Engine::Sparse(SparseGrid::new())),
+        ("hashlife", Engine::HashLife(HashLife::new())),
+    ]
+}
+
+fn place(engine: &mut Engine, cells: &[(i64, i64)], (x, y): (i64, i64)) {
+    for (cell_x, cell_y) in cells.iter() {
+        engine.set_cell(x + cell_x, y + cell_y, CellState::Alive(0));
+    }
+}
+
+fn moved(cells: &[(i64, i64)], (x, y): (i64, i64)) -> Vec<(i64, i64)> {
+    sorted(
+        cells
+            .iter()
+            .map(|(cell_x, cell_y)| (x + cell_x, y + cell_y))
+            .collect(),
+    )
+}
+
+fn sorted(mut cells: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    cells.sort();
+    cells
+}
+
+fn step(engine: &mut Engine, policy: &BorderPolicy, generations: usize) {
+    for _ in 0..generations {
+        engine.step(&Rule::default(), policy, 1);
+    }
+}
+
+#[test]
+fn blinker_has_period_two() {
+    for (name, mut engine) in engines(16, 16) {
+        place(&mut engine, &BLINKER, (6, 6));
+        let vertical = moved(&[(1, 0), (1, 1), (1, 2)], (6, 6));
+
+        step(&mut engine, &BorderPolicy::Clamp, 1);
+        assert_eq!(sorted(engine.alive_cells()), vertical, "{}", name);
+        step(&mut engine, &BorderPolicy::Clamp, 1);
+        assert_eq!(
+            sorted(engine.alive_cells()),
+            moved(&BLINKER, (6, 6)),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn glider_moves_one_cell_diagonally_every_four_generations() {
+    for (name, mut engine) in engines(32, 32) {
+        place(&mut engine, &GLIDER, (2, 2));
+        for moves in 1..=5 {
+            step(&mut engine, &BorderPolicy::Clamp, 4);
+            let expected = moved(&GLIDER, (2 + moves, 2 + moves));
+            assert_eq!(
+                sorted(engine.alive_cells()),
+                expected,
+                "{} move {}",
+                name,
+                moves
+            );
+        }
+    }
+}
+
+#[test]
+fn still_lifes_are_unchanged() {
+    for still_life in [&BLOCK[..], &BEEHIVE, &LOAF, &BOAT, &TUB] {
+        for (name, mut engine) in engines(16, 16) {
+            place(&mut engine, still_life, (5, 5));
+            step(&mut engine, &BorderPolicy::Clamp, 10);
+            assert_eq!(
+                sorted(engine.alive_cells()),
+                moved(still_life, (5, 5)),
+                "{}",
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn r_pentomino_has_116_cells_at_generation_1103() {
+    // The dense grid is left out, too slow for that many generations on a grid
+    // large enough for the escaping gliders.
+    let engines = [
+        ("packed", Engine::Packed(PackedGrid::new(640, 640, false))),
+        ("sparse", Engine::Sparse(SparseGrid::new())),
+        ("hashlife", Engine::HashLife(HashLife::new())),
+    ];
+    for (name, mut engine) in engines {
+        place(&mut engine, &R_PENTOMINO, (320, 320));
+        step(&mut engine, &BorderPolicy::Clamp, 1103);
+        assert_eq!(engine.population(), 116, "{}", name);
+    }
+}
+
+#[test]
+fn hashlife_jumps_match_single_steps() {
+    let mut single = Simulation::new(64, 64);
+    single.set_border_policy(BorderPolicy::Unbounded);
+    single.randomize(7, 0.4);
+    let mut jumps = Simulation::new(64, 64);
+    jumps.set_border_policy(BorderPolicy::Unbounded);
+    jumps.randomize(7, 0.4);
+    jumps.set_hyperspeed(true).unwrap();
+
+    single.step_n(64);
+    jumps.step_pow2(6).unwrap();
+    assert_eq!(jumps.generation(), 64);
+    assert_eq!(jumps.population(), single.population());
+    assert_eq!(jumps.bounding_box(), single.bounding_box());
+}
+
+#[test]
+fn block_in_a_clamped_corner_is_unchanged() {
+    for (name, mut engine) in engines(8, 8).into_iter().take(2) {
+        place(&mut engine, &BLOCK, (0, 0));
+        place(&mut engine, &BLOCK, (6, 6));
+        step(&mut engine, &BorderPolicy::Clamp, 5);
+        let expected = sorted([moved(&BLOCK, (0, 0)), moved(&BLOCK, (6, 6))].concat());
+        assert_eq!(sorted(engine.alive_cells()), expected, "{}", name);
+    }
+}
+
+#[test]
+fn blinker_on_a_clamped_edge_loses_the_cell_past_it() {
+    for (name, mut engine) in engines(8, 8).into_iter().take(2) {
+        place(&mut engine, &[(0, 0), (1, 0), (2, 0)], (0, 0));
+        step(&mut engine, &BorderPolicy::Clamp, 1);
+        assert_eq!(
+            sorted(engine.alive_cells()),
+            vec![(1, 0), (1, 1)],
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn blinker_across_a_wrapped_corner_oscillates() {
+    for (name, mut engine) in engines(8, 8).into_iter().take(2) {
+        let horizontal = [(7, 0), (0, 0), (1, 0)];
+        place(&mut engine, &horizontal, (0, 0));
+
+        step(&mut engine, &BorderPolicy::Wrap, 1);
+        assert_eq!(
+            sorted(engine.alive_cells()),
+            vec![(0, 0), (0, 1), (0, 7)],
+            "{}",
+            name
+        );
+        step(&mut engine, &BorderPolicy::Wrap, 1);
+        assert_eq!(
+            sorted(engine.alive_cells()),
+            moved(&horizontal, (0, 0)),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn glider_goes_around_a_wrapped_grid() {
+    // Widths on both sides of a word boundary of the packed grid.
+    for size in [8, 63, 64, 65] {
+        for (name, mut engine) in engines(size, size).into_iter().take(2) {
+            // Straddling the corner, so that the glider crosses both edges at once.
+            let glider: Vec<_> = moved(&GLIDER, (size as i64 - 2, size as i64 - 2))
+                .into_iter()
+                .map(|(x, y)| (x % size as i64, y % size as i64))
+                .collect();
+            place(&mut engine, &glider, (0, 0));
+            step(&mut engine, &BorderPolicy::Wrap, 4 * size);
+            assert_eq!(
+                sorted(engine.alive_cells()),
+                sorted(glider),
+                "{} {}",
+                name,
+                size
+            );
+        }
+    }
+}
+
+#[test]
+fn bounded_engines_agree_on_random_soups() {
+    for policy in [BorderPolicy::Clamp, BorderPolicy::Wrap] {
+        for (width, height) in [(1, 1), (13, 7), (64, 20), (70, 100)] {
+            let mut grid = DoubleBufferGrid::new(width, height);
+            grid.randomize(3, 0.4);
+            let mut packed = PackedGrid::new(width, height, true);
+            packed.randomize(3, 0.4);
+
+            for generation in 0..30 {
+                grid.step(&Rule::default(), policy.clone());
+                // A few threads, each band having to read across its seams.
+                packed.step_in_bands(&Rule::default(), policy.clone(), 3);
+                assert_eq!(
+                    packed.cells(),
+                    grid.cells(),
+                    "{:?} {}x{} generation {}",
+                    policy,
+                    width,
+                    height,
+                    generation
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn empty_bounded_universes_step() {
+    for (width, height) in [(0, 5), (5, 0), (0, 0)] {
+        for (name, mut engine) in engines(width, height).into_iter().take(2) {
+            for threads in [1, 3] {
+                engine.step(&Rule::default(), &BorderPolicy::Wrap, threads);
+            }
+            assert_eq!(engine.population(), 0, "{} {}x{}", name, width, height);
+        }
+        let mut simulation = Simulation::new(width, height);
+        simulation.step();
+        assert_eq!(simulation.generation(), 1);
+    }
+}